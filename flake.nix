@@ -131,12 +131,16 @@
                   StartLimitBurst = 5;
                 };
                 serviceConfig = {
-                  Type = "simple";
+                  Type = "notify";
                   ExecStart = "${cfg.package}/bin/asus-px-keyboard-tool /etc/asus-px-keyboard-tool.conf";
+                  ExecReload = "${pkgs.coreutils}/bin/kill -HUP $MAINPID";
                   TimeoutSec = 5;
+                  WatchdogSec = 30;
                   Restart = "on-failure";
-                  # Create and manage /var/lib/asus-px-kb-tool
+                  # Create and manage /var/lib/asus-px-kb-tool and /run/asus-px-kb-tool,
+                  # passed to the tool as $STATE_DIRECTORY and $RUNTIME_DIRECTORY
                   StateDirectory = "asus-px-kb-tool";
+                  RuntimeDirectory = "asus-px-kb-tool";
                 };
               };
             };
@@ -13,7 +13,6 @@ else
   exit 1
 fi
 
-install -dv /var/lib/asus-px-kb-tool/
 install -m 644 -v asus-px-keyboard-tool.service /etc/systemd/system/
 
 if [ -f /etc/asus-px-keyboard-tool.conf ]; then
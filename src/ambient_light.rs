@@ -0,0 +1,229 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::apkt_config::{AmbientConfig, AmbientStep};
+use crate::{backlight_idle, kb_illumination};
+use tracing::{info, warn};
+
+static IIO_ROOT: &str = "/sys/bus/iio/devices";
+
+// manual override from the cycle key.  Some(None) means until the next lid open / resume
+static OVERRIDE: Mutex<Option<Option<Instant>>> = Mutex::new(None);
+static RUNNING: AtomicBool = AtomicBool::new(false);
+// set after a resume, the level may have been changed behind the loop's back
+static REAPPLY: AtomicBool = AtomicBool::new(false);
+
+// find the first iio device with an illuminance channel
+pub fn find_sensor(iio_root: &str) -> Option<String> {
+    let mut paths: Vec<String> = std::fs::read_dir(iio_root).ok()?
+        .flatten()
+        .map(|entry| entry.path().to_string_lossy().to_string())
+        .filter(|path| std::path::Path::new(&format!("{}/in_illuminance_raw", path)).is_file())
+        .collect();
+    paths.sort();
+    paths.into_iter().next()
+}
+
+// raw * scale + offset, as documented for iio sysfs channels
+pub fn read_lux(sensor_path: &str) -> Option<f64> {
+    let read_value = |name: &str| -> Option<f64> {
+        std::fs::read_to_string(format!("{}/{}", sensor_path, name)).ok()?
+            .trim().parse::<f64>().ok()
+    };
+    let raw = read_value("in_illuminance_raw")?;
+    let scale = read_value("in_illuminance_scale").unwrap_or(1.0);
+    let offset = read_value("in_illuminance_offset").unwrap_or(0.0);
+    Some((raw + offset) * scale)
+}
+
+// called when the brightness is changed by hand
+pub fn set_manual_override(override_secs: Option<u64>) {
+    let mut manual_override = OVERRIDE.lock().expect("Failed to lock ambient override");
+    *manual_override = Some(override_secs.map(|secs| Instant::now() + Duration::from_secs(secs)));
+    info!(target: "backlight", "Ambient backlight paused by manual brightness change");
+}
+
+// called on lid open / resume
+pub fn clear_manual_override() {
+    let mut manual_override = OVERRIDE.lock().expect("Failed to lock ambient override");
+    if manual_override.take().is_some() {
+        info!(target: "backlight", "Ambient backlight resumed");
+    }
+}
+
+// called on resume.  returns true if ambient control is running and sets the level on its next
+// sample, restoring the saved levels would only override it until then
+pub fn reapply() -> bool {
+    REAPPLY.store(true, Ordering::Relaxed);
+    RUNNING.load(Ordering::Relaxed)
+}
+
+fn override_active() -> bool {
+    let mut manual_override = OVERRIDE.lock().expect("Failed to lock ambient override");
+    match *manual_override {
+        Some(Some(until)) if Instant::now() >= until => {
+            *manual_override = None;
+            false
+        }
+        Some(_) => true,
+        None => false,
+    }
+}
+
+pub fn start_ambient_backlight(config: AmbientConfig) {
+    let mut steps = config.steps.clone();
+    if steps.is_empty() {
+        panic!("backlight.ambient.steps must not be empty");
+    }
+    steps.sort_by(|a, b| a.lux.total_cmp(&b.lux));
+    if !(0.0..=1.0).contains(&config.smoothing) {
+        panic!("backlight.ambient.smoothing must be between 0 and 1");
+    }
+
+    let sensor_path = match &config.sensor {
+        Some(sensor) => sensor.clone(),
+        None => match find_sensor(IIO_ROOT) {
+            Some(sensor) => sensor,
+            None => {
+                warn!(target: "backlight", "No ambient light sensor found in {}, ambient backlight disabled", IIO_ROOT);
+                return;
+            }
+        },
+    };
+    info!(target: "backlight", sensor = %sensor_path, "Using ambient light sensor");
+    RUNNING.store(true, Ordering::Relaxed);
+
+    tokio::spawn(async move {
+        let mut smoothed_lux: Option<f64> = None;
+        let mut current_step: Option<usize> = None;
+        loop {
+            tokio::time::sleep(Duration::from_millis(config.poll_interval_ms)).await;
+
+            let Some(lux) = read_lux(&sensor_path) else {
+                warn!(target: "backlight", sensor = %sensor_path, "Unable to read ambient light sensor");
+                continue;
+            };
+            // exponential moving average to ignore short flickers
+            let lux = match smoothed_lux {
+                Some(previous) => previous + config.smoothing * (lux - previous),
+                None => lux,
+            };
+            smoothed_lux = Some(lux);
+
+            if REAPPLY.swap(false, Ordering::Relaxed) {
+                current_step = None;
+            }
+            if override_active() || backlight_idle::is_faded() || kb_illumination::is_disabled() {
+                current_step = None; // re-evaluate from scratch once automatic control resumes
+                continue;
+            }
+
+            let step = next_step(&steps, current_step, lux, config.hysteresis);
+            if current_step != Some(step) {
+                info!(target: "backlight", lux = format!("{:.1}", lux), level = steps[step].level, "Setting keyboard backlight from ambient light");
+                kb_illumination::set_all_levels(steps[step].level);
+                current_step = Some(step);
+            }
+        }
+    });
+}
+
+// pick the step for the given lux.  steps are sorted by lux, each one applies from its lux value
+// upwards.  leaving the current step needs the lux to clear its neighbouring boundary by the
+// hysteresis fraction, after that it goes straight to the target step even across several steps
+fn next_step(steps: &[AmbientStep], current: Option<usize>, lux: f64, hysteresis: f64) -> usize {
+    let target = steps.iter().rposition(|step| lux >= step.lux).unwrap_or(0);
+    let Some(current) = current else {
+        return target;
+    };
+    let moves_up = target > current && lux >= steps[current + 1].lux * (1.0 + hysteresis);
+    let moves_down = target < current && lux < steps[current].lux * (1.0 - hysteresis);
+    if moves_up || moves_down {
+        target
+    } else {
+        current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn steps() -> Vec<AmbientStep> {
+        vec![
+            AmbientStep { lux: 0.0, level: 3 },
+            AmbientStep { lux: 100.0, level: 2 },
+            AmbientStep { lux: 1000.0, level: 1 },
+            AmbientStep { lux: 10000.0, level: 0 },
+        ]
+    }
+
+    #[test]
+    fn finds_the_first_illuminance_sensor() {
+        let iio = TempDir::new("iio");
+        iio.write("iio:device0/in_accel_x_raw", "0\n");
+        iio.write("iio:device2/in_illuminance_raw", "10\n");
+        iio.write("iio:device1/in_illuminance_raw", "20\n");
+        assert_eq!(find_sensor(iio.path()), Some(format!("{}/iio:device1", iio.path())));
+    }
+
+    #[test]
+    fn no_sensor_without_illuminance() {
+        let iio = TempDir::new("iio");
+        iio.write("iio:device0/in_accel_x_raw", "0\n");
+        iio.create_dir("iio:device1/in_illuminance_raw");
+        assert_eq!(find_sensor(iio.path()), None);
+        assert_eq!(find_sensor(&format!("{}/missing", iio.path())), None);
+    }
+
+    #[test]
+    fn reads_raw_lux() {
+        let iio = TempDir::new("iio");
+        let sensor = iio.create_dir("iio:device0");
+        iio.write("iio:device0/in_illuminance_raw", "250\n");
+        assert_eq!(read_lux(&sensor), Some(250.0));
+    }
+
+    #[test]
+    fn applies_scale_and_offset() {
+        let iio = TempDir::new("iio");
+        let sensor = iio.create_dir("iio:device0");
+        iio.write("iio:device0/in_illuminance_raw", "250\n");
+        iio.write("iio:device0/in_illuminance_scale", "0.5\n");
+        iio.write("iio:device0/in_illuminance_offset", "10\n");
+        assert_eq!(read_lux(&sensor), Some(130.0));
+    }
+
+    #[test]
+    fn unreadable_lux() {
+        let iio = TempDir::new("iio");
+        let sensor = iio.create_dir("iio:device0");
+        assert_eq!(read_lux(&sensor), None);
+        iio.write("iio:device0/in_illuminance_raw", "bogus\n");
+        assert_eq!(read_lux(&sensor), None);
+    }
+
+    #[test]
+    fn first_reading_picks_the_target() {
+        assert_eq!(next_step(&steps(), None, 50.0, 0.1), 0);
+        assert_eq!(next_step(&steps(), None, 100.0, 0.1), 1);
+        assert_eq!(next_step(&steps(), None, 5000.0, 0.1), 2);
+    }
+
+    #[test]
+    fn hysteresis_keeps_the_step_near_a_boundary() {
+        assert_eq!(next_step(&steps(), Some(0), 105.0, 0.1), 0);
+        assert_eq!(next_step(&steps(), Some(0), 111.0, 0.1), 1);
+        assert_eq!(next_step(&steps(), Some(1), 95.0, 0.1), 1);
+        assert_eq!(next_step(&steps(), Some(1), 89.0, 0.1), 0);
+    }
+
+    #[test]
+    fn jumps_straight_to_the_target_step() {
+        // just above the last boundary, far past the one next to the current step
+        assert_eq!(next_step(&steps(), Some(0), 10050.0, 0.1), 3);
+        assert_eq!(next_step(&steps(), Some(3), 50.0, 0.1), 0);
+        assert_eq!(next_step(&steps(), Some(3), 950.0, 0.1), 1);
+    }
+}
@@ -1,7 +1,9 @@
+use std::collections::BTreeMap;
 use config::{File, FileFormat};
 use evdev::KeyCode;
 use evdev_rs::enums::EV_KEY;
 use serde::Deserialize;
+use crate::remap_model::validate_bpf_config;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Remap {
@@ -9,6 +11,23 @@ pub struct Remap {
     pub to: u32,
 }
 
+// drop presses of `scancode` that come within `ms` of the last one, see debounce_map in bpf/hid_modify.bpf.c
+#[derive(Debug, Deserialize, Clone)]
+pub struct Debounce {
+    pub scancode: u32,
+    pub ms: u32,
+}
+
+// full-report rule, see struct remap_rule in bpf/hid_modify.bpf.h
+#[derive(Debug, Deserialize, Clone)]
+pub struct RemapRule {
+    pub report_id: u32,
+    pub offset: u32,
+    pub value: u32,
+    pub mask: Option<u32>,
+    pub to: u32,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct CompatibilityConfig {
     pub hid_path_override: Option<String>,
@@ -21,6 +40,7 @@ pub struct FnLockConfig {
     pub enabled: bool,
     pub keycode: String,
     pub boot_default: String,
+    pub backend: String,
     #[serde(skip)]
     pub keycode_enum: Option<KeyCode>,
 }
@@ -32,68 +52,207 @@ pub struct ConfigWrapper {
     pub bpf: BpfConfig,
     pub tablet_kb_backlight_disable: TabletKbBacklightDisableConfig,
     pub kb_brightness_cycle: KbBrightnessConfig,
+    pub backlight: BacklightConfig,
+    pub paths: PathsConfig,
+    pub privileges: PrivilegesConfig,
+    pub exit_state: ExitStateConfig,
+    pub logging: LoggingConfig,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LoggingConfig {
+    pub level: String,
+    pub journald: String,
+    #[serde(default)]
+    pub modules: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExitStateConfig {
+    pub enabled: bool,
+    pub fnlock: String,
+    pub backlight: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PrivilegesConfig {
+    pub enabled: bool,
+    pub user: String,
+    pub keep_capabilities: Vec<String>,
+    pub seccomp: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PathsConfig {
+    pub state_dir: Option<String>,
+    pub runtime_dir: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct BpfConfig {
     pub enabled: bool,
+    pub backend: String,
+    pub map_type: String,
+    pub map_size: Option<u32>,
     pub remaps: Vec<Remap>,
+    pub rules: Vec<RemapRule>,
+    pub report_ids: Vec<u32>,
+    pub log_ignore: Vec<u32>,
+    pub debounce: Vec<Debounce>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct KbBrightnessConfig {
     pub enabled: bool,
     pub keycode: String,
+    pub sequence: Option<Vec<u32>>,
+    pub mode: String,
+    pub skip_zero: bool,
     #[serde(skip)]
     pub keycode_enum: Option<KeyCode>,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct BacklightConfig {
+    pub led: Option<LedConfig>,
+    pub boot_default: String,
+    pub idle_timeout_secs: Option<u64>,
+    pub idle_timeout_ac_secs: Option<u64>,
+    pub idle_timeout_battery_secs: Option<u64>,
+    pub ambient: AmbientConfig,
+}
+
+// one led name or path, or a list of them
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum LedConfig {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl LedConfig {
+    pub fn names(&self) -> Vec<String> {
+        match self {
+            LedConfig::One(name) => vec![name.clone()],
+            LedConfig::Many(names) => names.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AmbientConfig {
+    pub enabled: bool,
+    pub sensor: Option<String>,
+    pub steps: Vec<AmbientStep>,
+    pub hysteresis: f64,
+    pub smoothing: f64,
+    pub poll_interval_ms: u64,
+    pub override_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AmbientStep {
+    pub lux: f64,
+    pub level: u32,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct TabletKbBacklightDisableConfig {
     pub enabled: bool,
 }
 
 pub fn get_config(path: &str) -> ConfigWrapper {
+    try_get_config(path).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// like get_config, but returns errors instead of panicking.  used when reloading on SIGHUP
+pub fn try_get_config(path: &str) -> Result<ConfigWrapper, String> {
     let settings = config::Config::builder()
         // Load defaults from embedded string
         .add_source(File::from_str(DEFAULT_CONFIG, FileFormat::Toml))
         .add_source(config::File::with_name(path).format(FileFormat::Toml))
         .build()
-        .unwrap();
-    let mut config = settings.try_deserialize::<ConfigWrapper>().unwrap();
+        .map_err(|e| format!("Unable to read config {}: {}", path, e))?;
+    let mut config = settings.try_deserialize::<ConfigWrapper>()
+        .map_err(|e| format!("Invalid config {}: {}", path, e))?;
 
     if config.kb_brightness_cycle.enabled {
-        let ev_key = EV_KEY::from(config.kb_brightness_cycle.keycode.parse()
-            .expect("Invalid kb_brightness keycode in config"));
+        let ev_key = config.kb_brightness_cycle.keycode.parse::<EV_KEY>()
+            .map_err(|_| "Invalid kb_brightness keycode in config".to_string())?;
         let key_code = KeyCode::new(ev_key as u16);
         config.kb_brightness_cycle.keycode_enum = Some(key_code);
     }
 
+    if config.bpf.enabled {
+        validate_bpf_config(&config.bpf)?;
+    }
+
     if config.fnlock.enabled {
-        let ev_key = EV_KEY::from(config.fnlock.keycode.parse()
-            .expect("Invalid fnlock keycode in config"));
+        let ev_key = config.fnlock.keycode.parse::<EV_KEY>()
+            .map_err(|_| "Invalid fnlock keycode in config".to_string())?;
         let key_code = KeyCode::new(ev_key as u16);
         config.fnlock.keycode_enum = Some(key_code);
     }
-    
-    config // return
+
+    Ok(config)
 }
 
 static DEFAULT_CONFIG: &str = r#"
+[backlight]
+boot_default = "last" # "last" or a brightness level
+
+[backlight.ambient]
+enabled = false
+steps = [
+    { lux = 0.0, level = 3 },
+    { lux = 20.0, level = 2 },
+    { lux = 100.0, level = 1 },
+    { lux = 400.0, level = 0 },
+]
+hysteresis = 0.2
+smoothing = 0.3
+poll_interval_ms = 1000
+
 [bpf]
 enabled = false
+backend = "auto" # "auto", "bpf" or "hidraw"
+map_type = "hash" # "hash" or "array"
 remaps = []
+rules = []
+report_ids = [0x5a]
+log_ignore = [0xec]
+debounce = []
 
 [compatibility]
 
+[logging]
+level = "info"
+journald = "auto" # "auto" (when started by systemd), "on", "off"
+
+[paths]
+
+[privileges]
+enabled = false
+user = "asus-px-kb-tool"
+keep_capabilities = []
+seccomp = true
+
+[exit_state]
+enabled = false
+fnlock = "keep" # "keep", "on", "off"
+backlight = "keep" # "keep" or a brightness level
+
 [fnlock]
 enabled = false
 keycode = "KEY_PROG3"
 boot_default = "last" # "last", "on", "off"
+backend = "auto" # "sysfs", "hid", "emulated", "auto" (sysfs, then hid)
 
 [kb_brightness_cycle]
 enabled = false
 keycode = "KEY_PROG4"
+mode = "wrap" # "wrap", "bounce", "up_only"
+skip_zero = false
 
 [tablet_kb_backlight_disable]
 enabled = false
@@ -0,0 +1,235 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use evdev::AbsoluteAxisCode;
+use crate::apkt_config::BacklightConfig;
+use crate::{kb_illumination, privileges};
+use tracing::{info, warn};
+
+static POWER_SUPPLY_ROOT: &str = "/sys/class/power_supply";
+static FADE_STEP_DELAY: Duration = Duration::from_millis(150);
+
+struct IdleState {
+    last_activity: Instant,
+    // levels from before the backlight was faded out, None while not idle
+    saved_levels: Option<Vec<(String, u32)>>,
+}
+
+static IDLE_STATE: Mutex<Option<IdleState>> = Mutex::new(None);
+// event nodes of the touchpads being watched
+static TOUCHPADS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn idle_enabled(config: &BacklightConfig) -> bool {
+    config.idle_timeout_secs.is_some()
+        || config.idle_timeout_ac_secs.is_some()
+        || config.idle_timeout_battery_secs.is_some()
+}
+
+// called for every key press and touchpad event.  restores the backlight if it was faded out
+pub fn notify_activity() {
+    let mut idle_state = IDLE_STATE.lock().expect("Failed to lock idle state");
+    if let Some(idle_state) = idle_state.as_mut() {
+        idle_state.last_activity = Instant::now();
+        if let Some(levels) = idle_state.saved_levels.take() {
+            info!(target: "backlight", "Input activity, restoring keyboard backlight");
+            kb_illumination::restore_levels(&levels);
+        }
+    }
+}
+
+// true while the backlight is faded out
+pub fn is_faded() -> bool {
+    let idle_state = IDLE_STATE.lock().expect("Failed to lock idle state");
+    idle_state.as_ref().is_some_and(|s| s.saved_levels.is_some())
+}
+
+pub fn start_idle_tracking(config: BacklightConfig) {
+    {
+        let mut idle_state = IDLE_STATE.lock().expect("Failed to lock idle state");
+        *idle_state = Some(IdleState { last_activity: Instant::now(), saved_levels: None });
+    }
+    rediscover_touchpads();
+
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+
+            let timeout = if on_ac_power(POWER_SUPPLY_ROOT) {
+                config.idle_timeout_ac_secs.or(config.idle_timeout_secs)
+            } else {
+                config.idle_timeout_battery_secs.or(config.idle_timeout_secs)
+            };
+            let Some(timeout) = timeout else {
+                continue; // no timeout for the current power source
+            };
+
+            let levels = {
+                let mut idle_state = IDLE_STATE.lock().expect("Failed to lock idle state");
+                let idle_state = idle_state.as_mut().expect("Idle state not initialized");
+                if idle_state.saved_levels.is_some()
+                    || idle_state.last_activity.elapsed() < Duration::from_secs(timeout) {
+                    continue;
+                }
+                let levels = kb_illumination::current_levels();
+                idle_state.saved_levels = Some(levels.clone());
+                levels
+            };
+
+            info!(target: "backlight", timeout_secs = timeout, "No input, fading out keyboard backlight");
+            let highest = levels.iter().map(|(_, level)| *level).max().unwrap_or(0);
+            for step in (0..highest).rev() {
+                // stop fading if input came in while we were sleeping
+                {
+                    let idle_state = IDLE_STATE.lock().expect("Failed to lock idle state");
+                    if idle_state.as_ref().is_some_and(|s| s.saved_levels.is_none()) {
+                        break;
+                    }
+                    fade_to(&levels, step);
+                }
+                tokio::time::sleep(FADE_STEP_DELAY).await;
+            }
+        }
+    });
+}
+
+// one step of the fade: no led brighter than step
+fn fade_to(levels: &[(String, u32)], step: u32) {
+    for (led_path, level) in levels {
+        kb_illumination::set_level(led_path, (*level).min(step));
+    }
+}
+
+// touchpads are separate event devices, open them just to watch for activity.  called again from
+// the hotplug path, a touchpad that came back after resume or a rebind gets a new event node
+pub fn rediscover_touchpads() {
+    if IDLE_STATE.lock().expect("Failed to lock idle state").is_none() {
+        return; // idle tracking not started
+    }
+    let mut touchpads = TOUCHPADS.lock().expect("Failed to lock touchpads");
+    let mut enumerator = udev::Enumerator::new().expect("Failed to create udev enumerator");
+    enumerator.match_subsystem("input").unwrap();
+    enumerator.match_property("ID_INPUT_TOUCHPAD", "1").unwrap();
+
+    for device in enumerator.scan_devices().expect("Failed to scan devices") {
+        let Some(devnode) = device.devnode() else {
+            continue;
+        };
+        let path = devnode.to_string_lossy().to_string();
+        if !path.contains("event") || touchpads.contains(&path) {
+            continue;
+        }
+        let input_dev = match privileges::open_event_device(&path) {
+            Ok(input_dev) => input_dev,
+            Err(e) => {
+                warn!(target: "backlight", device = %path, "Failed to open touchpad: {}", e);
+                continue;
+            }
+        };
+        if !input_dev.supported_absolute_axes()
+            .is_some_and(|axes| axes.contains(AbsoluteAxisCode::ABS_MT_POSITION_X)) {
+            continue;
+        }
+        info!(target: "backlight", device = %path, "Watching touchpad for backlight idle timeout");
+        touchpads.push(path.clone());
+        tokio::spawn(async move {
+            let mut stream = input_dev.into_event_stream()
+                .expect("Failed to create event stream");
+            while stream.next_event().await.is_ok() {
+                notify_activity();
+            }
+            info!(target: "backlight", device = %path, "Touchpad disconnected");
+            TOUCHPADS.lock().expect("Failed to lock touchpads").retain(|touchpad| *touchpad != path);
+        });
+    }
+}
+
+// true if any mains power supply is online.  systems without one are treated as on ac
+fn on_ac_power(power_supply_root: &str) -> bool {
+    let Ok(entries) = std::fs::read_dir(power_supply_root) else {
+        return true;
+    };
+    let mut has_mains = false;
+    for entry in entries.flatten() {
+        let supply_path = entry.path();
+        let supply_type = std::fs::read_to_string(supply_path.join("type")).unwrap_or_default();
+        if supply_type.trim() != "Mains" {
+            continue;
+        }
+        has_mains = true;
+        let online = std::fs::read_to_string(supply_path.join("online")).unwrap_or_default();
+        if online.trim() == "1" {
+            return true;
+        }
+    }
+    !has_mains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn brightness(led_path: &str) -> String {
+        std::fs::read_to_string(format!("{}/brightness", led_path)).unwrap().trim().to_string()
+    }
+
+    #[test]
+    fn on_ac_with_mains_online() {
+        let root = TempDir::new("power-ac");
+        root.write("BAT0/type", "Battery\n");
+        root.write("ADP0/type", "Mains\n");
+        root.write("ADP0/online", "0\n");
+        root.write("ADP1/type", "Mains\n");
+        root.write("ADP1/online", "1\n");
+        assert!(on_ac_power(root.path()));
+    }
+
+    #[test]
+    fn on_battery_with_mains_offline() {
+        let root = TempDir::new("power-battery");
+        root.write("BAT0/type", "Battery\n");
+        root.write("AC/type", "Mains\n");
+        root.write("AC/online", "0\n");
+        assert!(!on_ac_power(root.path()));
+    }
+
+    #[test]
+    fn on_ac_without_mains() {
+        let root = TempDir::new("power-none");
+        root.write("BAT0/type", "Battery\n");
+        assert!(on_ac_power(root.path()));
+        assert!(on_ac_power(&format!("{}/missing", root.path())));
+    }
+
+    #[test]
+    fn fades_every_led_down() {
+        let leds = TempDir::new("fade-leds");
+        let levels: Vec<(String, u32)> = [("kbd", 3), ("kbd_1", 1)].iter()
+            .map(|(name, level)| {
+                leds.write(&format!("{}/brightness", name), &format!("{}\n", level));
+                (format!("{}/{}", leds.path(), name), *level)
+            })
+            .collect();
+
+        fade_to(&levels, 2);
+        assert_eq!((brightness(&levels[0].0), brightness(&levels[1].0)), ("2".to_string(), "1".to_string()));
+        fade_to(&levels, 0);
+        assert_eq!((brightness(&levels[0].0), brightness(&levels[1].0)), ("0".to_string(), "0".to_string()));
+    }
+
+    #[test]
+    fn activity_restores_faded_levels() {
+        let leds = TempDir::new("fade-restore");
+        let led_path = leds.create_dir("kbd");
+        leds.write("kbd/brightness", "0\n");
+        *IDLE_STATE.lock().unwrap() = Some(IdleState {
+            last_activity: Instant::now(),
+            saved_levels: Some(vec![(led_path.clone(), 3)]),
+        });
+        assert!(is_faded());
+
+        notify_activity();
+        assert!(!is_faded());
+        assert_eq!(brightness(&led_path), "3");
+        IDLE_STATE.lock().unwrap().take();
+    }
+}
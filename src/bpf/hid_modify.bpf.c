@@ -3,6 +3,8 @@
 #include <bpf/bpf_tracing.h>
 #include "hid_modify.bpf.h"
 
+// scancode remaps, keyed by the scancode.  bpf.map_type picks which one the loader fills, the other
+// stays empty.  max_entries of the hash is set from the config before loading
 struct {
     __uint(type, BPF_MAP_TYPE_HASH);
     __type(key, u32);
@@ -10,29 +12,200 @@ struct {
     __uint(max_entries, 32);
 } remap_map SEC(".maps");
 
+struct {
+    __uint(type, BPF_MAP_TYPE_ARRAY);
+    __type(key, u32);
+    __type(value, u32);
+    __uint(max_entries, STATS_ENTRIES);
+} remap_array SEC(".maps");
+
+// full-report rules, filled from index 0.  the first disabled entry ends the list
+struct {
+    __uint(type, BPF_MAP_TYPE_ARRAY);
+    __type(key, u32);
+    __type(value, struct remap_rule);
+    __uint(max_entries, MAX_RULES);
+} rule_map SEC(".maps");
+
+// report ids that have rules, so reports without any only need MIN_REPORT_LEN bytes.  used as a set
+struct {
+    __uint(type, BPF_MAP_TYPE_HASH);
+    __type(key, u32);
+    __type(value, u8);
+    __uint(max_entries, MAX_RULES);
+} rule_report_id_map SEC(".maps");
+
+// report ids whose byte 1 is a scancode, from bpf.report_ids.  used as a set
+struct {
+    __uint(type, BPF_MAP_TYPE_HASH);
+    __type(key, u32);
+    __type(value, u8);
+    __uint(max_entries, MAX_REPORT_IDS);
+} report_id_map SEC(".maps");
+
+// scancodes that are remapped but never logged, e.g. status reports.  from bpf.log_ignore
+struct {
+    __uint(type, BPF_MAP_TYPE_HASH);
+    __type(key, u32);
+    __type(value, u8);
+    __uint(max_entries, MAX_LOG_IGNORE);
+} log_ignore_map SEC(".maps");
+
+// debounce window in ns per scancode, 0 when the key isn't debounced.  from bpf.debounce
+struct {
+    __uint(type, BPF_MAP_TYPE_ARRAY);
+    __type(key, u32);
+    __type(value, u64);
+    __uint(max_entries, STATS_ENTRIES);
+} debounce_map SEC(".maps");
+
+// bpf_ktime_get_ns() of the last press of each debounced scancode that was let through
+struct {
+    __uint(type, BPF_MAP_TYPE_ARRAY);
+    __type(key, u32);
+    __type(value, u64);
+    __uint(max_entries, STATS_ENTRIES);
+} last_press_map SEC(".maps");
+
+// counters for the stats command, pinned to bpffs while the program is attached
+struct {
+    __uint(type, BPF_MAP_TYPE_PERCPU_ARRAY);
+    __type(key, u32);
+    __type(value, struct scancode_stats);
+    __uint(max_entries, STATS_ENTRIES);
+} stats_map SEC(".maps");
+
+struct {
+    __uint(type, BPF_MAP_TYPE_PERCPU_ARRAY);
+    __type(key, u32);
+    __type(value, u64);
+    __uint(max_entries, MAX_RULES);
+} rule_hits_map SEC(".maps");
+
+struct {
+    __uint(type, BPF_MAP_TYPE_PERCPU_ARRAY);
+    __type(key, u32);
+    __type(value, u64);
+    __uint(max_entries, 1);
+} overrun_map SEC(".maps");
+
 struct{
     __uint(type, BPF_MAP_TYPE_RINGBUF);
     __uint(max_entries, 4096); // 4kb, needs to be mult of page size
 } event_rb SEC(".maps");
 
+// copy of the log entries for the record command, only written while tap_enabled_map is set.
+// entries that don't fit are lost without counting as overruns, the daemon still gets them
+struct{
+    __uint(type, BPF_MAP_TYPE_RINGBUF);
+    __uint(max_entries, 4096);
+} tap_rb SEC(".maps");
+
+struct {
+    __uint(type, BPF_MAP_TYPE_ARRAY);
+    __type(key, u32);
+    __type(value, u32);
+    __uint(max_entries, 1);
+} tap_enabled_map SEC(".maps");
+
 // Dummy instance to get skeleton to generate definition
 struct event_log_entry _dummy = {0};
 
+// returns 0 if the entry was queued, counts it as an overrun otherwise
+static int log_event(struct event_log_entry *entry)
+{
+    u32 key = 0;
+    u32 *tap;
+    u64 *overruns;
+
+    tap = bpf_map_lookup_elem(&tap_enabled_map, &key);
+    if (tap && *tap)
+        bpf_ringbuf_output(&tap_rb, entry, sizeof(struct event_log_entry), 0);
+
+    if (bpf_ringbuf_output(&event_rb, entry, sizeof(struct event_log_entry), 0) == 0)
+        return 0;
+    overruns = bpf_map_lookup_elem(&overrun_map, &key);
+    if (overruns)
+        *overruns += 1;
+    return 1;
+}
+
+// returns 1 if the press came within the debounce window of the last one let through
+static int debounce(u32 scancode, u64 now)
+{
+    u64 *window = bpf_map_lookup_elem(&debounce_map, &scancode);
+    u64 *last;
+
+    if (!window || !*window)
+        return 0;
+    last = bpf_map_lookup_elem(&last_press_map, &scancode);
+    if (!last)
+        return 0;
+    if (*last && now - *last < *window)
+        return 1;
+    *last = now;
+    return 0;
+}
+
+// returns 1 if a rule matched and the report was rewritten.  `window` is how many bytes of data
+// were fetched, inlined so the verifier sees it as a constant bound on the offset
+static __always_inline int apply_rules(struct hid_bpf_ctx *hid_ctx, __u8 *data, const u32 window, int log)
+{
+    for (u32 i = 0; i < MAX_RULES; i++) {
+        u32 key = i;
+        struct remap_rule *rule = bpf_map_lookup_elem(&rule_map, &key);
+        if (!rule || !rule->enabled)
+            break;
+        if (rule->report_id != data[0])
+            continue;
+        u32 offset = rule->offset;
+        if (offset >= window || offset >= hid_ctx->size)
+            continue;
+
+        __u8 original = data[offset];
+        if ((original & rule->mask) != rule->value)
+            continue;
+        data[offset] = (original & ~rule->mask) | (rule->to & rule->mask);
+
+        u64 *hits = bpf_map_lookup_elem(&rule_hits_map, &key);
+        if (hits)
+            *hits += 1;
+        if (!log)
+            return 1;
+        struct event_log_entry entry = {
+            .original = original,
+            .remapped = 1,
+            .new = data[offset],
+            .report_id = data[0],
+            .offset = offset,
+            .rule = i,
+            .reason = REASON_NONE,
+        };
+        log_event(&entry);
+        return 1; // first matching rule wins
+    }
+    return 0;
+}
+
 SEC("struct_ops/hid_bpf_device_event")
 int BPF_PROG(modify_hid_event, struct hid_bpf_ctx *hid_ctx)
 {
-    __u8* data = hid_bpf_get_data(hid_ctx, 0, 6);
+    // enough for the scancode remaps.  asking for more would fail on devices with shorter reports
+    __u8* data = hid_bpf_get_data(hid_ctx, 0, MIN_REPORT_LEN);
+    __u8* rule_data;
     int *value;
+    int hotkey, ignored = 0, matched;
+    u32 report_id, scancode = 0;
+    u64 now = 0;
+    struct scancode_stats *stats = 0;
 
     if (!data)
         return 0;
 
-    // we're only interested in report id 90, which are the hotkey buttons
-    if (data[0] != 0x5a)
-        return 0; // Keep original data for other report ids
-
-    if (data[1] == 0)
-        return 0; // ignore key releases
+    // scancode remaps are only for the configured report ids, usually 90 for the hotkey buttons.
+    // key releases (scancode 0) are passed through
+    report_id = data[0];
+    hotkey = bpf_map_lookup_elem(&report_id_map, &report_id) && hid_ctx->size >= MIN_REPORT_LEN && data[1] != 0;
 
     // bpf_printk("Event: %x, %x, %x, %x, %x, %x", data[0],
     //   data[1], data[2], data[3], data[4], data[5]);
@@ -41,17 +214,66 @@ int BPF_PROG(modify_hid_event, struct hid_bpf_ctx *hid_ctx)
         .original = data[1],
         .remapped = 0,
         .new = 0,
+        .report_id = data[0],
+        .offset = 1,
+        .rule = -1,
+        .reason = REASON_NONE,
     };
 
-    value = bpf_map_lookup_elem(&remap_map, &data[1]);
-    if (value)
+    // counted and debounced before the rules, so a press a rule rewrites still shows up in stats
+    if (hotkey) {
+        scancode = entry.original;
+        now = bpf_ktime_get_ns();
+        stats = bpf_map_lookup_elem(&stats_map, &scancode);
+        if (stats)
+            stats->seen += 1;
+        if (debounce(scancode, now)) {
+            entry.reason = REASON_DEBOUNCE;
+            if (stats)
+                stats->debounced += 1;
+            // always logged, even for log_ignore scancodes
+            if (log_event(&entry) && stats)
+                stats->dropped += 1;
+            return -1; // drop the report, hid-asus never sees the second press
+        }
+        if (stats)
+            stats->last_seen_ns = now;
+        // filtered here so ignored events never reach the ring buffer
+        ignored = bpf_map_lookup_elem(&log_ignore_map, &scancode) != 0;
+    }
+
+    if (bpf_map_lookup_elem(&rule_report_id_map, &report_id)) {
+        // the full window when the device's reports are big enough, else only the first bytes
+        rule_data = hid_bpf_get_data(hid_ctx, 0, RULE_REPORT_BYTES);
+        if (rule_data)
+            matched = apply_rules(hid_ctx, rule_data, RULE_REPORT_BYTES, !ignored);
+        else
+            matched = apply_rules(hid_ctx, data, MIN_REPORT_LEN, !ignored);
+        if (matched)
+            return 0;
+    }
+
+    if (!hotkey)
+        return 0; // Keep original data for other report ids
+
+    value = bpf_map_lookup_elem(&remap_array, &scancode);
+    if (!value || !(*value & REMAP_VALID))
+        value = bpf_map_lookup_elem(&remap_map, &scancode);
+    if (value && (*value & REMAP_VALID))
     {
-        entry.new = *value;
+        entry.new = *value & 0xff;
         entry.remapped = 1;
-        data[1] = *value; // remap the scancode if it exists in the map
+        data[1] = *value & 0xff; // remap the scancode if it exists in the map
     }
 
-    bpf_ringbuf_output(&event_rb, &entry, sizeof(struct event_log_entry), 0);
+    if (stats)
+        stats->remapped += entry.remapped;
+
+    if (ignored)
+        return 0;
+
+    if (log_event(&entry) && stats)
+        stats->dropped += 1;
 
     return 0;
 }
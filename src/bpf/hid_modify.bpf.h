@@ -6,11 +6,50 @@
 #define HID_MODIFY_BPF_H
 
 #define MAX_PATH 512
+#define MAX_RULES 32
+// bytes of each report the rules can look at.  only fetched for report ids that have rules
+#define RULE_REPORT_BYTES 16
+// bytes of a hotkey report the scancode remaps need
+#define MIN_REPORT_LEN 6
+#define MAX_REPORT_IDS 8
+#define MAX_LOG_IGNORE 32
+// one stats entry per possible scancode
+#define STATS_ENTRIES 256
+// remap map values are the new scancode with this bit set, so an array entry of 0 means "not remapped"
+#define REMAP_VALID 0x100
+
+// why a press was dropped, reason in struct event_log_entry
+#define REASON_NONE 0
+#define REASON_DEBOUNCE 1
 
 struct event_log_entry {
     int original;
     int remapped;
     int new;
+    int report_id;
+    int offset;
+    int rule; // index of the matching rule, -1 for scancode remaps
+    int reason; // REASON_*, the report was dropped unless REASON_NONE
+} ;
+
+// rewrites byte `offset` of report `report_id` when (byte & mask) == value.  only the masked bits
+// are replaced with those of `to`
+struct remap_rule {
+    __u8 enabled;
+    __u8 report_id;
+    __u8 offset;
+    __u8 value;
+    __u8 mask;
+    __u8 to;
+} ;
+
+// per cpu, summed up by the stats command
+struct scancode_stats {
+    __u64 seen;
+    __u64 remapped;
+    __u64 dropped; // log entry lost because the ring buffer was full
+    __u64 last_seen_ns; // bpf_ktime_get_ns(), CLOCK_MONOTONIC
+    __u64 debounced; // presses dropped by the debounce window
 } ;
 
 typedef struct {
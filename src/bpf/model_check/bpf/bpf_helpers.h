@@ -0,0 +1,14 @@
+// stand-in for libbpf's bpf_helpers.h, so hid_modify.bpf.c builds as a normal program.  the map
+// definitions become plain structs whose member types carry the map type, sizes and value type
+#define SEC(name)
+#define __uint(name, val) int (*name)[val]
+#define __type(name, val) typeof(val) *name
+#ifndef __always_inline
+#define __always_inline inline __attribute__((always_inline))
+#endif
+
+// implemented in prog.c
+void *bpf_map_lookup_elem(void *map, const void *key);
+long bpf_ringbuf_output(void *ringbuf, void *data, __u64 size, __u64 flags);
+__u64 bpf_ktime_get_ns(void);
+__u8 *hid_bpf_get_data(struct hid_bpf_ctx *ctx, unsigned int offset, const size_t size);
@@ -0,0 +1,2 @@
+// stand-in for libbpf's bpf_tracing.h, the program is called directly with its context
+#define BPF_PROG(name, args...) name(args)
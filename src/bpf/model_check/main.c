@@ -0,0 +1,69 @@
+// reads commands from stdin, one per line, and answers each event with a line on stdout:
+//   reset
+//   set <map> <key> <value as hex bytes>
+//   event <now_ns> <size> <RULE_REPORT_BYTES bytes as hex>
+//   -> <return value> <report as hex> <log count> [<7 log entry fields> ...]
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define REPORT_BYTES 16
+#define MAX_LOGS 8
+#define LOG_FIELDS 7
+
+void harness_reset(void);
+int harness_set(const char *name, unsigned int key, const unsigned char *value, unsigned int len);
+int harness_event(unsigned char *data, int size, unsigned long long now, int *out_logs, int *out_log_count);
+
+static int parse_hex(const char *hex, unsigned char *out, int max)
+{
+    int len = 0;
+    while (hex[0] && hex[1] && len < max) {
+        unsigned int byte;
+        if (sscanf(hex, "%2x", &byte) != 1)
+            return -1;
+        out[len++] = byte;
+        hex += 2;
+    }
+    return len;
+}
+
+int main(void)
+{
+    char line[512], name[64], hex[256];
+    unsigned int key;
+    unsigned long long now;
+    int size;
+
+    while (fgets(line, sizeof(line), stdin)) {
+        if (strncmp(line, "reset", 5) == 0) {
+            harness_reset();
+        } else if (sscanf(line, "set %63s %u %255s", name, &key, hex) == 3) {
+            unsigned char value[64];
+            int len = parse_hex(hex, value, sizeof(value));
+            if (len < 0 || harness_set(name, key, value, len) != 0) {
+                fprintf(stderr, "bad set: %s", line);
+                return 1;
+            }
+        } else if (sscanf(line, "event %llu %d %255s", &now, &size, hex) == 3) {
+            unsigned char report[REPORT_BYTES];
+            int logs[MAX_LOGS * LOG_FIELDS], log_count, ret;
+            if (parse_hex(hex, report, REPORT_BYTES) != REPORT_BYTES) {
+                fprintf(stderr, "bad report: %s", line);
+                return 1;
+            }
+            ret = harness_event(report, size, now, logs, &log_count);
+            printf("%d ", ret);
+            for (int i = 0; i < REPORT_BYTES; i++)
+                printf("%02x", report[i]);
+            printf(" %d", log_count);
+            for (int i = 0; i < log_count * LOG_FIELDS; i++)
+                printf(" %d", logs[i]);
+            printf("\n");
+        } else {
+            fprintf(stderr, "bad command: %s", line);
+            return 1;
+        }
+    }
+    return 0;
+}
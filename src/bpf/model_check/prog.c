@@ -0,0 +1,152 @@
+// hid_modify.bpf.c built as a normal program, with the maps and helpers in plain memory.  used by
+// the test in remap_model.rs that checks the program and the model agree.  no libc headers here,
+// they clash with vmlinux.h
+#include "hid_modify.bpf.c"
+
+#define MAX_VALUE_SIZE 64
+#define MAX_ENTRIES 2048
+#define MAX_LOGS 8
+
+struct fake_map {
+    const char *name;
+    void *map;
+    int array;
+    u32 max_entries;
+    u32 value_size;
+};
+
+#define FAKE_MAP(map_name) { \
+    .name = #map_name, \
+    .map = &map_name, \
+    .array = sizeof(*map_name.type) / sizeof(int) != BPF_MAP_TYPE_HASH, \
+    .max_entries = sizeof(*map_name.max_entries) / sizeof(int), \
+    .value_size = sizeof(*map_name.value), \
+}
+
+static struct fake_map fake_maps[] = {
+    FAKE_MAP(remap_map),
+    FAKE_MAP(remap_array),
+    FAKE_MAP(rule_map),
+    FAKE_MAP(rule_report_id_map),
+    FAKE_MAP(report_id_map),
+    FAKE_MAP(log_ignore_map),
+    FAKE_MAP(debounce_map),
+    FAKE_MAP(last_press_map),
+    FAKE_MAP(stats_map),
+    FAKE_MAP(rule_hits_map),
+    FAKE_MAP(overrun_map),
+    FAKE_MAP(tap_enabled_map),
+};
+
+struct fake_entry {
+    void *map;
+    u32 key;
+    u8 value[MAX_VALUE_SIZE] __attribute__((aligned(8)));
+};
+
+static struct fake_entry entries[MAX_ENTRIES];
+static int entry_count;
+static u8 report[RULE_REPORT_BYTES];
+static u64 now_ns;
+static struct event_log_entry logs[MAX_LOGS];
+static int log_count;
+
+static struct fake_map *find_map(void *map)
+{
+    for (unsigned long i = 0; i < sizeof(fake_maps) / sizeof(fake_maps[0]); i++)
+        if (fake_maps[i].map == map)
+            return &fake_maps[i];
+    return 0;
+}
+
+// the entry of a key, created if asked to.  array entries always exist, hash entries once set
+static struct fake_entry *find_entry(void *map, u32 key, int create)
+{
+    struct fake_map *fake = find_map(map);
+    u32 count = 0;
+
+    if (!fake || fake->value_size > MAX_VALUE_SIZE || (fake->array && key >= fake->max_entries))
+        return 0;
+    for (int i = 0; i < entry_count; i++) {
+        if (entries[i].map != map)
+            continue;
+        if (entries[i].key == key)
+            return &entries[i];
+        count++;
+    }
+    // hash maps hold max_entries keys of any value
+    if ((!create && !fake->array) || count == fake->max_entries || entry_count == MAX_ENTRIES)
+        return 0;
+    struct fake_entry *entry = &entries[entry_count++];
+    entry->map = map;
+    entry->key = key;
+    __builtin_memset(entry->value, 0, sizeof(entry->value));
+    return entry;
+}
+
+void *bpf_map_lookup_elem(void *map, const void *key)
+{
+    struct fake_entry *entry = find_entry(map, *(const u32 *)key, 0);
+    return entry ? entry->value : 0;
+}
+
+long bpf_ringbuf_output(void *ringbuf, void *data, __u64 size, __u64 flags)
+{
+    if (ringbuf != &event_rb)
+        return 0;
+    if (log_count == MAX_LOGS || size != sizeof(struct event_log_entry))
+        return -1;
+    __builtin_memcpy(&logs[log_count++], data, size);
+    return 0;
+}
+
+__u64 bpf_ktime_get_ns(void)
+{
+    return now_ns;
+}
+
+__u8 *hid_bpf_get_data(struct hid_bpf_ctx *ctx, unsigned int offset, const size_t size)
+{
+    if (offset + size > ctx->allocated_size)
+        return 0;
+    return report + offset;
+}
+
+// empty every map, like a freshly loaded program
+void harness_reset(void)
+{
+    entry_count = 0;
+}
+
+// returns 0, or -1 if there is no such map, key or room
+int harness_set(const char *name, u32 key, const u8 *value, u32 len)
+{
+    for (unsigned long i = 0; i < sizeof(fake_maps) / sizeof(fake_maps[0]); i++) {
+        struct fake_map *fake = &fake_maps[i];
+        if (__builtin_strcmp(fake->name, name) != 0)
+            continue;
+        struct fake_entry *entry = find_entry(fake->map, key, 1);
+        if (!entry || len != fake->value_size)
+            return -1;
+        __builtin_memcpy(entry->value, value, len);
+        return 0;
+    }
+    return -1;
+}
+
+// run the program on a report of `size` bytes in a buffer of RULE_REPORT_BYTES.  the report is
+// rewritten in place, the log entries are written to out_logs as 7 ints each
+int harness_event(u8 *data, int size, u64 now, int *out_logs, int *out_log_count)
+{
+    struct hid_bpf_ctx ctx = { .allocated_size = RULE_REPORT_BYTES, .size = size };
+    int ret;
+
+    __builtin_memcpy(report, data, RULE_REPORT_BYTES);
+    now_ns = now;
+    log_count = 0;
+    ret = modify_hid_event(&ctx);
+    __builtin_memcpy(data, report, RULE_REPORT_BYTES);
+    __builtin_memcpy(out_logs, logs, log_count * sizeof(struct event_log_entry));
+    *out_log_count = log_count;
+    return ret;
+}
@@ -1,14 +1,20 @@
-use crate::apkt_config::Remap;
+use crate::apkt_config::{BpfConfig, Debounce, RemapRule};
+use crate::sd_notify::{heartbeat, Heartbeat};
 use libbpf_rs::skel::OpenSkel;
 use libbpf_rs::skel::SkelBuilder;
-use libbpf_rs::{Link, MapCore, MapFlags};
+use libbpf_rs::{Link, MapCore, MapFlags, MapHandle, MapMut};
 use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::thread;
+use std::thread::JoinHandle;
 use std::time::Duration;
 extern crate plain;
 use crate::bpf_loader::hid_modify::types::event_log_entry;
 use plain::Plain;
 use hid_modify::*;
+use crate::remap_model::{log_entry, parse_debounce, parse_log_ignore, parse_report_ids, parse_rules, remap_map_size, validate_remaps, DropReason, LogEntry, MAX_REMAP_MAP_SIZE, MAX_RULES};
+use tracing::{error, info, warn};
 
 mod hid_modify {
     include!(concat!(
@@ -18,14 +24,51 @@ mod hid_modify {
 }
 
 unsafe impl Plain for event_log_entry {}
-static mut LINK: Option<Link> = None;
+// kept until stop_bpf.  dropping the link detaches the program
+static LINK: Mutex<Option<Link>> = Mutex::new(None);
+// duplicate of the remap map fd, so remaps can be changed after the skeleton is gone.  remap_map or
+// remap_array, depending on bpf.map_type at startup
+static REMAP_MAP: Mutex<Option<MapHandle>> = Mutex::new(None);
+static REMAP_MAP_SIZE: AtomicUsize = AtomicUsize::new(0);
+static REMAP_ARRAY: AtomicBool = AtomicBool::new(false);
+static RULE_MAP: Mutex<Option<MapHandle>> = Mutex::new(None);
+static RULE_REPORT_ID_MAP: Mutex<Option<MapHandle>> = Mutex::new(None);
+static REPORT_ID_MAP: Mutex<Option<MapHandle>> = Mutex::new(None);
+static LOG_IGNORE_MAP: Mutex<Option<MapHandle>> = Mutex::new(None);
+static DEBOUNCE_MAP: Mutex<Option<MapHandle>> = Mutex::new(None);
+// pinned while the program is attached so the stats command can read them
+static PINNED_MAPS: Mutex<Vec<MapHandle>> = Mutex::new(Vec::new());
+static POLL_THREAD: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+static STOP_POLLING: AtomicBool = AtomicBool::new(false);
+
+pub static PIN_DIR: &str = "/sys/fs/bpf/asus-px-keyboard-tool";
+static STATS_PIN: &str = "stats";
+static RULE_HITS_PIN: &str = "rule_hits";
+static OVERRUNS_PIN: &str = "ringbuf_overruns";
+// the record command reads tap_rb, and sets tap_enabled while it does.  event_rb stays the daemon's
+static TAP_PIN: &str = "tap";
+static TAP_ENABLED_PIN: &str = "tap_enabled";
+// STATS_ENTRIES and REMAP_VALID in bpf/hid_modify.bpf.h
+static STATS_ENTRIES: u32 = 256;
+static REMAP_VALID: u32 = 0x100;
+
+// load and attach the program.  nothing is left attached when it fails, so the caller can fall back
+// to remapping through hidraw
+pub fn start_bpf(hid_id: i32, config: &BpfConfig) -> Result<(), String> {
+    let map_size = remap_map_size(config).map_err(|e| format!("Invalid bpf config: {}", e))?;
+    let use_array = config.map_type == "array";
 
-pub fn start_bpf(hid_id: i32, remaps: &Vec<Remap>) {
     let skel_builder = HidModifySkelBuilder::default();
     let mut open_object = MaybeUninit::uninit();
-    let open_skel = skel_builder
+    let mut open_skel = skel_builder
         .open(&mut open_object)
-        .expect("Failed to open skel");
+        .map_err(|e| format!("Failed to open skel: {}", e))?;
+
+    // the program looks in both remap maps, the one not picked by bpf.map_type stays empty.
+    // remap_array always has an entry per scancode
+    let hash_size = if use_array { 1 } else { map_size as u32 };
+    open_skel.maps.remap_map.set_max_entries(hash_size)
+        .map_err(|e| format!("Failed to size remap map: {}", e))?;
 
     // set hid_id in bpf program
     let hid_modify_ops = open_skel.struct_ops.hid_modify_ops;
@@ -33,60 +76,379 @@ pub fn start_bpf(hid_id: i32, remaps: &Vec<Remap>) {
         (*hid_modify_ops).hid_id = hid_id;
     }
     let mut skel = open_skel.load()
-        .expect("Failed to load skel.  Are you root?");
+        .map_err(|e| format!("Failed to load skel.  Are you root? {}", e))?;
+
+    // everything that can fail is set up before attaching
+    let duplicate = |map: &MapMut<'_>, name: &str| {
+        MapHandle::try_from(map).map_err(|e| format!("Failed to duplicate {}: {}", name, e))
+    };
+    let remap_map = if use_array {
+        duplicate(&skel.maps.remap_array, "remap map")?
+    } else {
+        duplicate(&skel.maps.remap_map, "remap map")?
+    };
+    let rule_map = duplicate(&skel.maps.rule_map, "rule map")?;
+    let rule_report_id_map = duplicate(&skel.maps.rule_report_id_map, "rule report id map")?;
+    let report_id_map = duplicate(&skel.maps.report_id_map, "report id map")?;
+    let log_ignore_map = duplicate(&skel.maps.log_ignore_map, "log ignore map")?;
+    let debounce_map = duplicate(&skel.maps.debounce_map, "debounce map")?;
+
+    // set up the ring buffer
+    let mut builder = libbpf_rs::RingBufferBuilder::new();
+    builder
+        .add(&skel.maps.event_rb, |data| process_log_entry(data))
+        .map_err(|e| format!("Failed to add ringbuf: {}", e))?;
+    let ringbuf = builder.build().map_err(|e| format!("Failed to build ringbuf: {}", e))?;
+
     let link = skel
         .maps
         .hid_modify_ops
         .attach_struct_ops()
-        .expect("Failed to attach struct ops");
+        .map_err(|e| format!("Failed to attach struct ops: {}", e))?;
 
     // save the link to prevent it from being dropped
-    unsafe {
-        LINK = Some(link);
-    }
-    println!("BPF program loaded and attached");
+    *LINK.lock().expect("Failed to lock BPF link") = Some(link);
+    info!(target: "bpf", hid_id, "BPF program loaded and attached");
 
-    for remap in remaps {
-        println!("Remapping {:#04x} to {:#04x}", remap.from, remap.to);
-        skel.maps
-            .remap_map
-            .update(
-                &remap.from.to_ne_bytes(),
-                &remap.to.to_ne_bytes(),
-                MapFlags::ANY,
-            )
-            .expect("Failed to map remap");
-    }
+    REMAP_MAP_SIZE.store(map_size, Ordering::Relaxed);
+    REMAP_ARRAY.store(use_array, Ordering::Relaxed);
+    *REMAP_MAP.lock().expect("Failed to lock remap map") = Some(remap_map);
+    info!(target: "bpf", map_type = %config.map_type, map_size, "Remap map created");
+    *RULE_MAP.lock().expect("Failed to lock rule map") = Some(rule_map);
+    *RULE_REPORT_ID_MAP.lock().expect("Failed to lock rule report id map") = Some(rule_report_id_map);
+    *REPORT_ID_MAP.lock().expect("Failed to lock report id map") = Some(report_id_map);
+    *LOG_IGNORE_MAP.lock().expect("Failed to lock log ignore map") = Some(log_ignore_map);
+    *DEBOUNCE_MAP.lock().expect("Failed to lock debounce map") = Some(debounce_map);
+    set_remaps(config);
+    pin_maps(&skel);
 
-    // set up the ring buffer
-    let mut builder = libbpf_rs::RingBufferBuilder::new();
-    builder
-        .add(&skel.maps.event_rb, |data| process_log_entry(data))
-        .expect("failed to add ringbuf");
-    let ringbuf = builder.build().unwrap();
     let mutex = std::sync::Mutex::new(ringbuf);
 
-    // spawn a thread to poll the ring buffer indefinitely without blocking
-    thread::spawn(move || {
-        loop {
+    // spawn a thread to poll the ring buffer until stop_bpf without blocking.  the poll times out
+    // regularly so the watchdog can tell the thread is still alive
+    let poll_thread = thread::spawn(move || {
+        while !STOP_POLLING.load(Ordering::Relaxed) {
             let lock = mutex.lock().expect("BPF: Failed to lock mutex");
-            let res = lock.poll(Duration::MAX);
+            let res = lock.poll(Duration::from_secs(1));
             if res.is_err() {
-                eprintln!("BPF: Error polling ring buffer: {:?}", res.err());
+                error!(target: "bpf", "Error polling ring buffer: {:?}", res.err());
             }
+            heartbeat(Heartbeat::Bpf);
         }
     });
+    *POLL_THREAD.lock().expect("Failed to lock BPF poll thread") = Some(poll_thread);
+    Ok(())
 }
 
-fn process_log_entry(data: &[u8]) -> i32 {
-    let event = plain::from_bytes::<event_log_entry>(data).unwrap();
-    if event.original == 0xec {
-        return 0; // ignore status events
+// replace the contents of the remap, rule, filter and debounce maps
+pub fn set_remaps(config: &BpfConfig) {
+    set_rules(&config.rules);
+    set_debounce(&config.debounce);
+    match parse_report_ids(&config.report_ids) {
+        Ok(report_ids) => set_keys(&REPORT_ID_MAP, &report_ids),
+        Err(e) => error!(target: "bpf", "Keeping the current report ids: {}", e),
+    }
+    match parse_log_ignore(&config.log_ignore) {
+        Ok(log_ignore) => set_keys(&LOG_IGNORE_MAP, &log_ignore),
+        Err(e) => error!(target: "bpf", "Keeping the current log_ignore list: {}", e),
     }
-    if event.remapped == 1{
-        println!("BPF: Remapped scancode: {:#04x} -> {:#04x}", event.original, event.new);
+
+    let remap_map = REMAP_MAP.lock().expect("Failed to lock remap map");
+    let Some(remap_map) = remap_map.as_ref() else {
+        return; // bpf not started
+    };
+    // the map can't grow after loading, more remaps need a restart
+    if let Err(e) = validate_remaps(&config.remaps, REMAP_MAP_SIZE.load(Ordering::Relaxed)) {
+        error!(target: "bpf", "Keeping the current remaps: {}", e);
+        return;
+    }
+    if REMAP_ARRAY.load(Ordering::Relaxed) {
+        // array entries can't be deleted, the scancodes no longer remapped are reset to 0
+        for scancode in 0..MAX_REMAP_MAP_SIZE as u32 {
+            if !config.remaps.iter().any(|remap| remap.from == scancode) {
+                update_remap(remap_map, scancode, 0);
+            }
+        }
     } else {
-        println!("BPF: Unmapped scancode: {:#04x}", event.original);
+        clear_map(remap_map);
+    }
+    for remap in &config.remaps {
+        info!(target: "bpf", from = %format!("{:#04x}", remap.from), to = %format!("{:#04x}", remap.to), "Remapping scancode");
+        update_remap(remap_map, remap.from, remap.to | REMAP_VALID);
+    }
+}
+
+fn update_remap(remap_map: &MapHandle, scancode: u32, value: u32) {
+    if let Err(e) = remap_map.update(&scancode.to_ne_bytes(), &value.to_ne_bytes(), MapFlags::ANY) {
+        error!(target: "bpf", scancode = %format!("{:#04x}", scancode), "Failed to set remap: {}", e);
+    }
+}
+
+// stale pins from a crashed run are replaced.  failures only cost the stats command
+fn pin_maps(skel: &HidModifySkel<'_>) {
+    if let Err(e) = std::fs::create_dir_all(PIN_DIR) {
+        warn!(target: "bpf", "Unable to create {}, stats are unavailable (is bpffs mounted?): {}", PIN_DIR, e);
+        return;
+    }
+    let maps = [
+        (&skel.maps.stats_map, STATS_PIN),
+        (&skel.maps.rule_hits_map, RULE_HITS_PIN),
+        (&skel.maps.overrun_map, OVERRUNS_PIN),
+        (&skel.maps.tap_rb, TAP_PIN),
+        (&skel.maps.tap_enabled_map, TAP_ENABLED_PIN),
+    ];
+    let mut pinned = PINNED_MAPS.lock().expect("Failed to lock pinned maps");
+    for (map, name) in maps {
+        let path = format!("{}/{}", PIN_DIR, name);
+        let _ = std::fs::remove_file(&path);
+        let pin = MapHandle::try_from(map).and_then(|mut handle| handle.pin(&path).map(|_| handle));
+        match pin {
+            Ok(handle) => pinned.push(handle),
+            Err(e) => warn!(target: "bpf", "Unable to pin {}: {}", path, e),
+        }
+    }
+}
+
+fn unpin_maps() {
+    for name in [STATS_PIN, RULE_HITS_PIN, OVERRUNS_PIN, TAP_PIN, TAP_ENABLED_PIN] {
+        let path = format!("{}/{}", PIN_DIR, name);
+        if let Err(e) = std::fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                warn!(target: "bpf", "Unable to unpin {}: {}", path, e);
+            }
+        }
+    }
+    let _ = std::fs::remove_dir(PIN_DIR);
+    PINNED_MAPS.lock().expect("Failed to lock pinned maps").clear();
+}
+
+fn clear_map(map: &MapHandle) {
+    let old_keys: Vec<Vec<u8>> = map.keys().collect();
+    for key in old_keys {
+        if let Err(e) = map.delete(&key) {
+            warn!(target: "bpf", "Failed to remove map entry: {}", e);
+        }
+    }
+}
+
+// fill a u32 -> u8 hash map used as a set
+fn set_keys(map: &Mutex<Option<MapHandle>>, keys: &[u8]) {
+    let map = map.lock().expect("Failed to lock map");
+    let Some(map) = map.as_ref() else {
+        return; // bpf not started
+    };
+    clear_map(map);
+    for key in keys {
+        if let Err(e) = map.update(&(*key as u32).to_ne_bytes(), &[1u8], MapFlags::ANY) {
+            error!(target: "bpf", "Failed to add {:#04x}: {}", key, e);
+        }
     }
+}
+
+// rules are written from index 0 and the rest of the array is cleared, the program stops at the
+// first disabled entry.  their report ids go to rule_report_id_map, the program only fetches the
+// whole rule window for those
+fn set_rules(rules: &[RemapRule]) {
+    let rules = match parse_rules(rules) {
+        Ok(rules) => rules,
+        Err(e) => {
+            error!(target: "bpf", "Keeping the current rules: {}", e);
+            return;
+        }
+    };
+    let report_ids: Vec<u8> = rules.iter().map(|rule| rule.report_id).collect();
+    set_keys(&RULE_REPORT_ID_MAP, &report_ids);
+    let rule_map = RULE_MAP.lock().expect("Failed to lock rule map");
+    let Some(rule_map) = rule_map.as_ref() else {
+        return; // bpf not started
+    };
+    for index in 0..MAX_RULES {
+        let value = match rules.get(index) {
+            Some(rule) => {
+                info!(
+                    target: "bpf",
+                    report_id = %format!("{:#04x}", rule.report_id),
+                    offset = rule.offset,
+                    value = %format!("{:#04x}", rule.value),
+                    mask = %format!("{:#04x}", rule.mask),
+                    to = %format!("{:#04x}", rule.to),
+                    "Adding rule"
+                );
+                rule.to_bytes()
+            }
+            None => [0u8; 6],
+        };
+        if let Err(e) = rule_map.update(&(index as u32).to_ne_bytes(), &value, MapFlags::ANY) {
+            error!(target: "bpf", "Failed to set rule {}: {}", index, e);
+        }
+    }
+}
+
+// every scancode has an entry, the ones not debounced are set to 0
+fn set_debounce(debounce: &[Debounce]) {
+    let debounce_map = DEBOUNCE_MAP.lock().expect("Failed to lock debounce map");
+    let Some(debounce_map) = debounce_map.as_ref() else {
+        return; // bpf not started
+    };
+    let windows = match parse_debounce(debounce) {
+        Ok(windows) => windows,
+        Err(e) => {
+            error!(target: "bpf", "Keeping the current debounce windows: {}", e);
+            return;
+        }
+    };
+    for scancode in 0..STATS_ENTRIES {
+        let window = windows.iter()
+            .find(|(debounced, _)| *debounced as u32 == scancode)
+            .map(|(_, window)| *window)
+            .unwrap_or(0);
+        if window > 0 {
+            info!(target: "bpf", scancode = %format!("{:#04x}", scancode), ms = window / 1_000_000, "Debouncing scancode");
+        }
+        if let Err(e) = debounce_map.update(&scancode.to_ne_bytes(), &window.to_ne_bytes(), MapFlags::ANY) {
+            error!(target: "bpf", scancode = %format!("{:#04x}", scancode), "Failed to set debounce window: {}", e);
+        }
+    }
+}
+
+// stop the ring buffer thread, then detach the program.  the remaps stop applying after this
+pub fn stop_bpf() {
+    STOP_POLLING.store(true, Ordering::Relaxed);
+    if let Some(poll_thread) = POLL_THREAD.lock().expect("Failed to lock BPF poll thread").take() {
+        // finishes its current poll, at most a second
+        let _ = poll_thread.join();
+    }
+    if let Some(link) = LINK.lock().expect("Failed to lock BPF link").take() {
+        if let Err(e) = link.detach() {
+            error!(target: "bpf", "Failed to detach program: {}", e);
+        }
+        info!(target: "bpf", "BPF program detached");
+    }
+    REMAP_MAP.lock().expect("Failed to lock remap map").take();
+    RULE_MAP.lock().expect("Failed to lock rule map").take();
+    RULE_REPORT_ID_MAP.lock().expect("Failed to lock rule report id map").take();
+    REPORT_ID_MAP.lock().expect("Failed to lock report id map").take();
+    LOG_IGNORE_MAP.lock().expect("Failed to lock log ignore map").take();
+    DEBOUNCE_MAP.lock().expect("Failed to lock debounce map").take();
+    unpin_maps();
+}
+
+// sum of a u64 at `offset` in every cpu's copy of the value
+fn sum_percpu(values: &[Vec<u8>], offset: usize) -> u64 {
+    values.iter()
+        .filter_map(|value| value.get(offset..offset + 8))
+        .map(|bytes| u64::from_ne_bytes(bytes.try_into().unwrap()))
+        .sum()
+}
+
+fn max_percpu(values: &[Vec<u8>], offset: usize) -> u64 {
+    values.iter()
+        .filter_map(|value| value.get(offset..offset + 8))
+        .map(|bytes| u64::from_ne_bytes(bytes.try_into().unwrap()))
+        .max()
+        .unwrap_or(0)
+}
+
+fn lookup_percpu(map: &MapHandle, key: u32) -> Vec<Vec<u8>> {
+    map.lookup_percpu(&key.to_ne_bytes(), MapFlags::ANY).ok().flatten().unwrap_or_default()
+}
+
+// stats: print the counters the running daemon's bpf program keeps
+pub fn print_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let open = |name: &str| {
+        let path = format!("{}/{}", PIN_DIR, name);
+        MapHandle::from_pinned_path(&path)
+            .map_err(|e| format!("Unable to open {} (is the daemon running with the bpf backend, and are you root?): {}", path, e))
+    };
+    let stats_map = open(STATS_PIN)?;
+    let rule_hits_map = open(RULE_HITS_PIN)?;
+    let overrun_map = open(OVERRUNS_PIN)?;
+
+    let mut now = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now);
+    }
+    let now_ns = now.tv_sec as u64 * 1_000_000_000 + now.tv_nsec as u64;
+
+    // struct scancode_stats: seen, remapped, dropped, last_seen_ns, debounced
+    println!("{:<10} {:>10} {:>10} {:>10} {:>10} {:>14}", "scancode", "seen", "remapped", "debounced", "dropped", "last seen");
+    for scancode in 0..STATS_ENTRIES {
+        let values = lookup_percpu(&stats_map, scancode);
+        let seen = sum_percpu(&values, 0);
+        if seen == 0 {
+            continue;
+        }
+        let last_seen_ns = max_percpu(&values, 24);
+        let ago = now_ns.saturating_sub(last_seen_ns) as f64 / 1e9;
+        println!(
+            "{:<10} {:>10} {:>10} {:>10} {:>10} {:>13.1}s ago",
+            format!("{:#04x}", scancode),
+            seen,
+            sum_percpu(&values, 8),
+            sum_percpu(&values, 32),
+            sum_percpu(&values, 16),
+            ago
+        );
+    }
+
+    for rule in 0..MAX_RULES as u32 {
+        let hits = sum_percpu(&lookup_percpu(&rule_hits_map, rule), 0);
+        if hits > 0 {
+            println!("rule {}: {} hit(s)", rule, hits);
+        }
+    }
+    println!("ring buffer overruns: {}", sum_percpu(&lookup_percpu(&overrun_map, 0), 0));
+    Ok(())
+}
+
+// read the log entries of the running daemon's program until `stop` is set or on_event fails.  they
+// come from tap_rb, which the program only fills while this runs, so the daemon still logs them all
+pub fn poll_tap_events<F>(stop: &AtomicBool, mut on_event: F) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: FnMut(&LogEntry) -> std::io::Result<()>,
+{
+    let open = |name: &str| {
+        let path = format!("{}/{}", PIN_DIR, name);
+        MapHandle::from_pinned_path(&path)
+            .map_err(|e| format!("Unable to open {} (is the daemon running with the bpf backend, and are you root?): {}", path, e))
+    };
+    let tap_rb = open(TAP_PIN)?;
+    let tap_enabled = open(TAP_ENABLED_PIN)?;
+    let mut builder = libbpf_rs::RingBufferBuilder::new();
+    builder.add(&tap_rb, |data| match on_event(&parse_log_entry(data)) {
+        Ok(()) => 0,
+        Err(_) => -1, // stops the poll below
+    })?;
+    let ringbuf = builder.build()?;
+
+    let key = 0u32.to_ne_bytes();
+    tap_enabled.update(&key, &1u32.to_ne_bytes(), MapFlags::ANY)?;
+    let mut result = Ok(());
+    while !stop.load(Ordering::Relaxed) {
+        if let Err(e) = ringbuf.poll(Duration::from_millis(200)) {
+            result = Err(e.into());
+            break;
+        }
+    }
+    // only one recording at a time, a second one would turn the tap off for the first
+    tap_enabled.update(&key, &0u32.to_ne_bytes(), MapFlags::ANY)?;
+    result
+}
+
+fn parse_log_entry(data: &[u8]) -> LogEntry {
+    let event = plain::from_bytes::<event_log_entry>(data).unwrap();
+    LogEntry {
+        original: event.original as u8,
+        remapped: event.remapped == 1,
+        new: event.new as u8,
+        report_id: event.report_id as u8,
+        offset: event.offset as u8,
+        rule: u8::try_from(event.rule).ok(),
+        reason: DropReason::from_code(event.reason),
+    }
+}
+
+fn process_log_entry(data: &[u8]) -> i32 {
+    log_entry(&parse_log_entry(data));
     0 // return value
 }
@@ -0,0 +1,75 @@
+// subcommands that replace the default daemon mode
+static COMMANDS: [&str; 10] = [
+    "status", "open-helper", "install-udev-rules", "doctor", "simulate", "simulate-remap", "stats", "record",
+    "replay", "describe-device",
+];
+pub static DEFAULT_CONFIG_PATH: &str = "asus-px-keyboard-tool.conf";
+
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub command: Option<String>,
+    pub command_args: Vec<String>,
+    pub config_path: Option<String>,
+    pub state_dir: Option<String>,
+    pub runtime_dir: Option<String>,
+}
+
+pub fn parse_args(args: &[String]) -> Result<CliArgs, String> {
+    let mut cli_args = CliArgs::default();
+    let mut positional: Vec<String> = Vec::new();
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--state-dir" => {
+                cli_args.state_dir = Some(iter.next().ok_or("--state-dir needs a path")?.clone());
+            }
+            "--runtime-dir" => {
+                cli_args.runtime_dir = Some(iter.next().ok_or("--runtime-dir needs a path")?.clone());
+            }
+            _ if arg.starts_with("--") => return Err(format!("Unknown option {}", arg)),
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    if let Some(first) = positional.first() {
+        if COMMANDS.contains(&first.as_str()) {
+            cli_args.command = Some(positional.remove(0));
+            cli_args.command_args = positional;
+            return Ok(cli_args);
+        }
+    }
+    if positional.len() > 1 {
+        return Err("Too many arguments".to_string());
+    }
+    cli_args.config_path = positional.pop();
+    Ok(cli_args)
+}
+
+pub fn print_usage(program: &str) {
+    println!("Usage: {} [--state-dir <dir>] [--runtime-dir <dir>] [config_path]", program);
+    println!("       {} [--runtime-dir <dir>] status [config_path]", program);
+    println!("       {} install-udev-rules [group] [rules_path|-]", program);
+    println!("       {} doctor", program);
+    println!("       {} simulate [scancode...]", program);
+    println!("       {} simulate-remap <report> [config_path]", program);
+    println!("       {} stats", program);
+    println!("       {} record <file> [event_device...]", program);
+    println!("       {} replay <file> [hidraw|evdev]", program);
+    println!("       {} describe-device [report_descriptor]", program);
+}
+
+// $STATE_DIRECTORY / $RUNTIME_DIRECTORY can hold several colon separated paths, use the first
+pub fn systemd_directory(variable: &str) -> Option<String> {
+    let value = std::env::var(variable).ok()?;
+    value.split(':').next()
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| dir.to_string())
+}
+
+// explicit arguments win, then the config, then the directories systemd created for us
+pub fn resolve_directory(cli_dir: &Option<String>, config_dir: &Option<String>, systemd_variable: &str) -> Option<String> {
+    cli_dir.clone()
+        .or_else(|| config_dir.clone())
+        .or_else(|| systemd_directory(systemd_variable))
+}
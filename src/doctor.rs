@@ -0,0 +1,380 @@
+use crate::fn_lock::{find_sysfs_fn_lock, SYSFS_ROOT};
+use crate::hid::{find_vendor_interface, hidraw_node, ASUS_IDS};
+use crate::kb_illumination::{discover_leds, LEDS_ROOT};
+use crate::privileges;
+
+static VMLINUX_BTF: &str = "/sys/kernel/btf/vmlinux";
+static HID_DEVICES_ROOT: &str = "/sys/bus/hid/devices";
+static INPUT_ROOT: &str = "/sys/class/input";
+// HID-BPF moved to struct_ops in 6.11
+static MIN_KERNEL: (u32, u32) = (6, 11);
+static SW_TABLET_MODE: u32 = 0x01;
+
+enum Outcome {
+    Pass,
+    Warn,
+    Fail,
+}
+
+struct Check {
+    name: &'static str,
+    outcome: Outcome,
+    detail: String,
+    remedy: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Check {
+        Check { name, outcome: Outcome::Pass, detail: detail.into(), remedy: None }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, remedy: impl Into<String>) -> Check {
+        Check { name, outcome: Outcome::Warn, detail: detail.into(), remedy: Some(remedy.into()) }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, remedy: impl Into<String>) -> Check {
+        Check { name, outcome: Outcome::Fail, detail: detail.into(), remedy: Some(remedy.into()) }
+    }
+}
+
+// check every prerequisite and print pass/fail with a remedy.  errors if any check failed
+pub fn run_doctor() -> Result<(), Box<dyn std::error::Error>> {
+    let bus_path = find_vendor_interface();
+    let checks = vec![
+        check_kernel_version(),
+        check_btf(),
+        check_hid_bpf_ops(),
+        check_bpffs(),
+        check_privileges(),
+        check_hid_device(bus_path.as_deref()),
+        check_hid_driver(bus_path.as_deref()),
+        check_hidraw(bus_path.as_deref()),
+        check_led(bus_path.as_deref()),
+        check_fn_lock_attribute(),
+        check_asus_nb_wmi(),
+        check_tablet_switch(),
+    ];
+
+    let mut failed = 0;
+    for check in &checks {
+        let label = match check.outcome {
+            Outcome::Pass => "PASS",
+            Outcome::Warn => "WARN",
+            Outcome::Fail => {
+                failed += 1;
+                "FAIL"
+            }
+        };
+        println!("[{}] {}: {}", label, check.name, check.detail);
+        if let Some(remedy) = &check.remedy {
+            println!("       fix: {}", remedy);
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} checks failed", failed, checks.len()).into());
+    }
+    println!("All checks passed");
+    Ok(())
+}
+
+fn check_kernel_version() -> Check {
+    let name = "kernel version";
+    let Ok(release) = std::fs::read_to_string("/proc/sys/kernel/osrelease") else {
+        return Check::fail(name, "unable to read /proc/sys/kernel/osrelease", "is /proc mounted?");
+    };
+    let release = release.trim();
+    let mut parts = release.split(|c: char| !c.is_ascii_digit()).filter_map(|part| part.parse::<u32>().ok());
+    let version = (parts.next().unwrap_or(0), parts.next().unwrap_or(0));
+    if version >= MIN_KERNEL {
+        Check::pass(name, release)
+    } else {
+        Check::fail(
+            name,
+            format!("{} is older than {}.{}", release, MIN_KERNEL.0, MIN_KERNEL.1),
+            format!("HID-BPF struct_ops needs Linux {}.{} or newer, update the kernel", MIN_KERNEL.0, MIN_KERNEL.1),
+        )
+    }
+}
+
+fn check_btf() -> Check {
+    let name = "kernel BTF";
+    if std::path::Path::new(VMLINUX_BTF).is_file() {
+        Check::pass(name, VMLINUX_BTF)
+    } else {
+        Check::fail(name, format!("{} is missing", VMLINUX_BTF), "use a kernel built with CONFIG_DEBUG_INFO_BTF=y")
+    }
+}
+
+fn check_hid_bpf_ops() -> Check {
+    let name = "hid_bpf_ops in BTF";
+    let Ok(btf) = std::fs::read(VMLINUX_BTF) else {
+        return Check::fail(name, format!("unable to read {}", VMLINUX_BTF), "use a kernel built with CONFIG_DEBUG_INFO_BTF=y");
+    };
+    match btf_has_struct(&btf, "hid_bpf_ops") {
+        Some(true) => Check::pass(name, "struct hid_bpf_ops found"),
+        Some(false) => Check::fail(
+            name,
+            "struct hid_bpf_ops not found",
+            "use a kernel built with CONFIG_HID_BPF=y (6.11 or newer)",
+        ),
+        None => Check::fail(name, format!("unable to parse {}", VMLINUX_BTF), "report this as a bug"),
+    }
+}
+
+// whether the running kernel can attach the struct_ops program in bpf/hid_modify.bpf.c
+pub fn hid_bpf_supported() -> bool {
+    std::fs::read(VMLINUX_BTF).ok()
+        .and_then(|btf| btf_has_struct(&btf, "hid_bpf_ops"))
+        .unwrap_or(false)
+}
+
+// true if the raw BTF blob defines a struct with the given name, None if it can't be parsed
+fn btf_has_struct(btf: &[u8], struct_name: &str) -> Option<bool> {
+    let u16_at = |offset: usize| -> Option<u16> { Some(u16::from_le_bytes(btf.get(offset..offset + 2)?.try_into().ok()?)) };
+    let u32_at = |offset: usize| -> Option<u32> { Some(u32::from_le_bytes(btf.get(offset..offset + 4)?.try_into().ok()?)) };
+    // only little endian BTF is handled, which covers the architectures this runs on
+    if u16_at(0)? != 0xeb9f {
+        return None;
+    }
+    let header_len = u32_at(4)? as usize;
+    let type_off = u32_at(8)? as usize;
+    let type_len = u32_at(12)? as usize;
+    let str_off = u32_at(16)? as usize;
+    let str_len = u32_at(20)? as usize;
+    let types = btf.get(header_len + type_off..header_len + type_off + type_len)?;
+    let strings = btf.get(header_len + str_off..header_len + str_off + str_len)?;
+
+    let name_at = |offset: usize| -> &[u8] {
+        let rest = strings.get(offset..).unwrap_or(&[]);
+        &rest[..rest.iter().position(|b| *b == 0).unwrap_or(rest.len())]
+    };
+
+    let mut offset = 0;
+    while offset + 12 <= types.len() {
+        let name_off = u32::from_le_bytes(types[offset..offset + 4].try_into().ok()?) as usize;
+        let info = u32::from_le_bytes(types[offset + 4..offset + 8].try_into().ok()?);
+        let vlen = (info & 0xffff) as usize;
+        let kind = (info >> 24) & 0x1f;
+        if kind == 4 && name_at(name_off) == struct_name.as_bytes() {
+            return Some(true);
+        }
+        // each kind is followed by kind specific data
+        let extra = match kind {
+            1 | 14 | 17 => 4,          // int, var, decl_tag
+            3 => 12,                   // array
+            4 | 5 => vlen * 12,        // struct, union members
+            6 => vlen * 8,             // enum values
+            13 => vlen * 8,            // func_proto params
+            15 | 19 => vlen * 12,      // datasec vars, enum64 values
+            2 | 7..=12 | 16 | 18 => 0, // ptr, fwd, typedef, modifiers, func, float, type_tag
+            _ => return None,
+        };
+        offset += 12 + extra;
+    }
+    Some(false)
+}
+
+fn check_bpffs() -> Check {
+    let name = "bpffs";
+    let mounts = std::fs::read_to_string("/proc/mounts").unwrap_or_default();
+    match mounts.lines().find(|line| line.split_whitespace().nth(2) == Some("bpf")) {
+        Some(line) => Check::pass(name, format!("mounted at {}", line.split_whitespace().nth(1).unwrap_or("?"))),
+        None => Check::warn(name, "not mounted", "mount -t bpf bpf /sys/fs/bpf"),
+    }
+}
+
+fn check_privileges() -> Check {
+    let name = "privileges";
+    if privileges::bpf_allowed() {
+        Check::pass(name, "CAP_BPF and CAP_PERFMON available")
+    } else {
+        Check::fail(
+            name,
+            "not root and missing CAP_BPF/CAP_PERFMON, remaps fall back to hidraw",
+            "run the service as root, or use install-udev-rules for the features that work without it",
+        )
+    }
+}
+
+fn check_hid_device(bus_path: Option<&str>) -> Check {
+    let name = "HID device";
+    if let Some(bus_path) = bus_path {
+        return Check::pass(name, format!("{} (vendor interface, usage page 0xff31)", bus_path));
+    }
+    let present = std::fs::read_dir(HID_DEVICES_ROOT).into_iter().flatten().flatten()
+        .any(|entry| entry.file_name().to_string_lossy().contains(ASUS_IDS));
+    if present {
+        Check::fail(
+            name,
+            format!("{} present, but no interface has the expected report descriptor", ASUS_IDS),
+            "set compatibility.hid_path_override and hid_id_override, and report your descriptor in an issue",
+        )
+    } else {
+        Check::fail(
+            name,
+            format!("no {} device in {}", ASUS_IDS, HID_DEVICES_ROOT),
+            "attach the keyboard; other models may need compatibility overrides",
+        )
+    }
+}
+
+fn check_hid_driver(bus_path: Option<&str>) -> Check {
+    let name = "hid-asus driver";
+    let Some(bus_path) = bus_path else {
+        return Check::fail(name, "no HID device", "see the HID device check");
+    };
+    let driver = std::fs::read_link(format!("{}/driver", bus_path)).ok()
+        .and_then(|driver| driver.file_name().map(|name| name.to_string_lossy().to_string()));
+    match driver {
+        Some(driver) if driver == "asus" || driver == "hid-asus" => Check::pass(name, format!("bound to {}", driver)),
+        Some(driver) => Check::warn(
+            name,
+            format!("bound to {} instead of hid-asus", driver),
+            "modprobe hid-asus, function keys may report different codes with the generic driver",
+        ),
+        None => Check::fail(name, "no driver bound", "modprobe hid-asus"),
+    }
+}
+
+fn check_hidraw(bus_path: Option<&str>) -> Check {
+    let name = "hidraw node";
+    let Some(bus_path) = bus_path else {
+        return Check::fail(name, "no HID device", "see the HID device check");
+    };
+    match hidraw_node(bus_path) {
+        Some(node) if privileges::can_access(&node, true) => Check::pass(name, format!("{} is accessible", node)),
+        Some(node) => Check::fail(
+            name,
+            format!("{} is not accessible", node),
+            "run as root or install the udev rules with install-udev-rules",
+        ),
+        None => Check::fail(name, "no hidraw node for the vendor interface", "modprobe hidraw"),
+    }
+}
+
+fn check_led(bus_path: Option<&str>) -> Check {
+    let name = "keyboard backlight";
+    let leds = discover_leds(LEDS_ROOT, bus_path.unwrap_or(""));
+    let Some(led) = leds.first() else {
+        return Check::fail(
+            name,
+            format!("no *kbd_backlight* led in {}", LEDS_ROOT),
+            "make sure hid-asus or asus-nb-wmi is loaded, or set backlight.led",
+        );
+    };
+    if privileges::can_access(&format!("{}/brightness", led), true) {
+        Check::pass(name, led.clone())
+    } else {
+        Check::fail(
+            name,
+            format!("{}/brightness is not writable", led),
+            "run as root or install the udev rules with install-udev-rules",
+        )
+    }
+}
+
+fn check_fn_lock_attribute() -> Check {
+    let name = "kernel fn_lock attribute";
+    match find_sysfs_fn_lock(SYSFS_ROOT) {
+        Some(path) => Check::pass(name, path),
+        None => Check::warn(name, "not available", "fnlock.backend \"hid\" or \"emulated\" is used instead, no action needed"),
+    }
+}
+
+static TABLET_SWITCH_REMEDY: &str =
+    "only needed for tablet_kb_backlight_disable.  boot with asus_nb_wmi.tablet_mode_sw=1, 2 or 3 depending on the model";
+
+fn check_asus_nb_wmi() -> Check {
+    let name = "asus_nb_wmi";
+    if !std::path::Path::new("/sys/module/asus_nb_wmi").exists() {
+        return Check::warn(name, "module not loaded", "modprobe asus-nb-wmi (needed for the tablet mode switch)");
+    }
+    match std::fs::read_to_string("/sys/module/asus_nb_wmi/parameters/tablet_mode_sw") {
+        // -1 leaves the tablet mode switch off on most models
+        Ok(value) if value.trim() == "-1" => Check::warn(name, "loaded, tablet_mode_sw=-1", TABLET_SWITCH_REMEDY),
+        Ok(value) => Check::pass(name, format!("loaded, tablet_mode_sw={}", value.trim())),
+        Err(_) => Check::pass(name, "loaded"),
+    }
+}
+
+fn check_tablet_switch() -> Check {
+    let name = "tablet mode switch";
+    for entry in std::fs::read_dir(INPUT_ROOT).into_iter().flatten().flatten() {
+        let Ok(switches) = std::fs::read_to_string(entry.path().join("capabilities/sw")) else {
+            continue;
+        };
+        // space separated hex words, lowest bits last
+        let lowest = switches.split_whitespace().last()
+            .and_then(|word| u64::from_str_radix(word, 16).ok())
+            .unwrap_or(0);
+        if lowest & (1 << SW_TABLET_MODE) != 0 {
+            let device = std::fs::read_to_string(entry.path().join("name")).unwrap_or_default();
+            return Check::pass(name, format!("reported by {}", device.trim()));
+        }
+    }
+    Check::warn(
+        name,
+        "no input device reports SW_TABLET_MODE",
+        TABLET_SWITCH_REMEDY,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // raw BTF with an int, a pointer, an enum, a func_proto and then the struct, so finding the
+    // struct means every kind before it was skipped by the right length
+    fn btf(struct_kind: u32) -> Vec<u8> {
+        let strings = b"\0int\0color\0red\0blue\0hid_bpf_ops\0flags\0arg\0";
+        let name = |name: &[u8]| strings.windows(name.len() + 2)
+            .position(|window| window[0] == 0 && &window[1..=name.len()] == name && window[name.len() + 1] == 0)
+            .unwrap() as u32 + 1;
+        let info = |kind: u32, vlen: u32| kind << 24 | vlen;
+        let types: Vec<u32> = [
+            vec![name(b"int"), info(1, 0), 4, 32],                                     // int, 32 bits
+            vec![0, info(2, 0), 1],                                                    // ptr to int
+            vec![name(b"color"), info(6, 2), 4, name(b"red"), 0, name(b"blue"), 1],   // enum
+            vec![0, info(13, 1), 1, name(b"arg"), 2],                                  // func_proto
+            vec![name(b"hid_bpf_ops"), info(struct_kind, 1), 8, name(b"flags"), 1, 0], // struct
+        ].concat();
+        let types: Vec<u8> = types.iter().flat_map(|word| word.to_le_bytes()).collect();
+
+        let mut blob = Vec::new();
+        blob.extend_from_slice(&0xeb9fu16.to_le_bytes());
+        blob.extend_from_slice(&[1, 0]); // version, flags
+        for word in [24u32, 0, types.len() as u32, types.len() as u32, strings.len() as u32] {
+            blob.extend_from_slice(&word.to_le_bytes());
+        }
+        blob.extend_from_slice(&types);
+        blob.extend_from_slice(strings);
+        blob
+    }
+
+    #[test]
+    fn finds_a_struct_after_other_kinds() {
+        let blob = btf(4);
+        assert_eq!(btf_has_struct(&blob, "hid_bpf_ops"), Some(true));
+        assert_eq!(btf_has_struct(&blob, "hid_bpf"), Some(false));
+        // an enum of that name isn't a struct
+        assert_eq!(btf_has_struct(&blob, "color"), Some(false));
+    }
+
+    #[test]
+    fn unions_are_not_structs() {
+        assert_eq!(btf_has_struct(&btf(5), "hid_bpf_ops"), Some(false));
+    }
+
+    #[test]
+    fn rejects_unparsable_btf() {
+        let blob = btf(4);
+        // big endian
+        let mut swapped = blob.clone();
+        swapped.swap(0, 1);
+        assert_eq!(btf_has_struct(&swapped, "hid_bpf_ops"), None);
+        assert_eq!(btf_has_struct(&blob[..blob.len() - 1], "hid_bpf_ops"), None);
+        assert_eq!(btf_has_struct(&blob[..10], "hid_bpf_ops"), None);
+        assert_eq!(btf_has_struct(&btf(31), "hid_bpf_ops"), None);
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use evdev::uinput::VirtualDevice;
+use evdev::{AttributeSet, Device, EventType, InputEvent, KeyCode, SynchronizationCode};
+use crate::apkt_config::FnLockConfig;
+use crate::hid::toggle_fn_lock;
+use crate::privileges;
+use crate::sysfs;
+use tracing::{error, info};
+
+// name given to the uinput device created by the emulated backend.  used to avoid picking up
+// our own virtual device as a new event device
+pub static EMULATED_DEVICE_NAME: &str = "asus-px-keyboard-tool fn-lock emulation";
+
+pub static SYSFS_ROOT: &str = "/sys";
+static UINPUT_PATH: &str = "/dev/uinput";
+
+// kernel-managed fn-lock attributes, relative to the sysfs root.  the first one that exists is used
+static SYSFS_FN_LOCK_ATTRIBUTES: [&str; 3] = [
+    "devices/platform/asus-nb-wmi/fn_lock",
+    "bus/platform/devices/asus-nb-wmi/fn_lock",
+    "class/firmware-attributes/asus-armoury/attributes/fn_lock/current_value",
+];
+
+// keys swapped by the emulated backend while fn-lock is on
+static EMULATED_SWAPS: [(KeyCode, KeyCode); 11] = [
+    (KeyCode::KEY_F1, KeyCode::KEY_MUTE),
+    (KeyCode::KEY_F2, KeyCode::KEY_VOLUMEDOWN),
+    (KeyCode::KEY_F3, KeyCode::KEY_VOLUMEUP),
+    (KeyCode::KEY_F4, KeyCode::KEY_MICMUTE),
+    (KeyCode::KEY_F6, KeyCode::KEY_SELECTIVE_SCREENSHOT),
+    (KeyCode::KEY_F7, KeyCode::KEY_BRIGHTNESSDOWN),
+    (KeyCode::KEY_F8, KeyCode::KEY_BRIGHTNESSUP),
+    (KeyCode::KEY_F9, KeyCode::KEY_SWITCHVIDEOMODE),
+    (KeyCode::KEY_F10, KeyCode::KEY_TOUCHPAD_TOGGLE),
+    (KeyCode::KEY_F11, KeyCode::KEY_SLEEP),
+    (KeyCode::KEY_F12, KeyCode::KEY_RFKILL),
+];
+
+#[derive(Debug, Clone)]
+pub enum FnLockBackend {
+    // fn-lock attribute exposed by asus-wmi, avoids hidraw writes entirely
+    Sysfs { attribute_path: String },
+    // firmware fn-lock via the 0x5a 0xd0 0x4e feature report
+    Hid { hidraw_path: String },
+    // software fn-lock: the keyboard's event devices are grabbed and keys are swapped through uinput
+    Emulated,
+}
+
+impl FnLockBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FnLockBackend::Sysfs { .. } => "sysfs",
+            FnLockBackend::Hid { .. } => "hid",
+            FnLockBackend::Emulated => "emulated",
+        }
+    }
+
+    pub fn apply(&self, state: bool) {
+        match self {
+            FnLockBackend::Sysfs { attribute_path } => {
+                write_sysfs_fn_lock(attribute_path, state);
+            }
+            FnLockBackend::Hid { hidraw_path } => {
+                toggle_fn_lock(hidraw_path, state);
+            }
+            FnLockBackend::Emulated => {
+                // nothing to send, the device tasks read the shared state on every key
+                info!(target: "hid", fn_lock = state, "Emulated Fn-Lock changed");
+            }
+        }
+    }
+
+    // false if the device or attribute can't be written, e.g. when running without root or the
+    // udev rules
+    pub fn is_usable(&self) -> bool {
+        match self {
+            FnLockBackend::Sysfs { attribute_path } => privileges::can_access(attribute_path, true),
+            FnLockBackend::Hid { hidraw_path } => privileges::can_access(hidraw_path, true),
+            FnLockBackend::Emulated => privileges::can_access(UINPUT_PATH, true),
+        }
+    }
+}
+
+// picks the backend without writing anything, the caller applies the boot state once
+pub fn select_backend(config: &FnLockConfig, hidraw_path: &str) -> FnLockBackend {
+    let hid_backend = FnLockBackend::Hid { hidraw_path: hidraw_path.to_string() };
+    match config.backend.as_str() {
+        "sysfs" => {
+            let attribute_path = find_sysfs_fn_lock(SYSFS_ROOT)
+                .expect("fnlock.backend is \"sysfs\" but no kernel fn_lock attribute was found");
+            FnLockBackend::Sysfs { attribute_path }
+        }
+        "hid" => hid_backend,
+        "emulated" => FnLockBackend::Emulated,
+        "auto" => {
+            // prefer the kernel attribute, then the firmware feature report.  emulation is never
+            // picked here: the feature report can't be read back, so there's no telling whether the
+            // firmware ignored it
+            let sysfs_attribute = find_sysfs_fn_lock(SYSFS_ROOT)
+                .filter(|attribute_path| privileges::can_access(attribute_path, true));
+            if let Some(attribute_path) = sysfs_attribute {
+                info!(target: "hid", attribute = %attribute_path, "Found kernel fn_lock attribute");
+                FnLockBackend::Sysfs { attribute_path }
+            } else {
+                hid_backend
+            }
+        }
+        _ => panic!("Invalid fnlock.backend value in config: {}", config.backend),
+    }
+}
+
+pub fn find_sysfs_fn_lock(sysfs_root: &str) -> Option<String> {
+    for attribute in SYSFS_FN_LOCK_ATTRIBUTES.iter() {
+        let path = format!("{}/{}", sysfs_root, attribute);
+        if std::path::Path::new(&path).is_file() {
+            return Some(path);
+        }
+    }
+    None
+}
+
+fn write_sysfs_fn_lock(attribute_path: &String, state: bool) -> bool {
+    match sysfs::write_attribute(attribute_path, if state { "1" } else { "0" }) {
+        Ok(_) => {
+            info!(target: "hid", attribute = %attribute_path, fn_lock = state, "Fn-Lock set through sysfs");
+            true
+        }
+        Err(e) => {
+            error!(target: "hid", attribute = %attribute_path, "Error writing fn_lock attribute: {}", e);
+            false
+        }
+    }
+}
+
+pub struct EmulatedFnLock {
+    virtual_device: VirtualDevice,
+    // keys currently held down, mapped to the code that was emitted for the press.  this keeps
+    // press/release pairs consistent when fn-lock is toggled while a key is held
+    pressed: HashMap<u16, u16>,
+    pending: Vec<InputEvent>,
+}
+
+impl EmulatedFnLock {
+    // grab the device and create the uinput device that replaces it.  returns None for devices
+    // that have neither function nor media keys, those are left alone.  the function keys and the
+    // media keys usually come from different devices of the keyboard, each gets its own emulation
+    pub fn attach(device: &mut Device) -> Option<EmulatedFnLock> {
+        let supported_keys = device.supported_keys()?;
+        if !supported_keys.iter().any(|key| swapped_code(key.code()) != key.code()) {
+            return None;
+        }
+
+        let mut keys = AttributeSet::<KeyCode>::new();
+        for key in supported_keys.iter() {
+            keys.insert(key);
+        }
+        for (fkey, media) in EMULATED_SWAPS.iter() {
+            keys.insert(*fkey);
+            keys.insert(*media);
+        }
+
+        // uinput can be unavailable, e.g. for devices plugged in after privileges were dropped
+        let virtual_device = VirtualDevice::builder()
+            .map(|builder| builder.name(EMULATED_DEVICE_NAME))
+            .and_then(|builder| builder.with_keys(&keys))
+            .and_then(|builder| match device.misc_properties() {
+                Some(misc) => builder.with_msc(misc),
+                None => Ok(builder),
+            })
+            .and_then(|builder| match device.supported_switches() {
+                Some(switches) => builder.with_switches(switches),
+                None => Ok(builder),
+            })
+            .and_then(|builder| match device.supported_relative_axes() {
+                Some(axes) => builder.with_relative_axes(axes),
+                None => Ok(builder),
+            })
+            .and_then(|builder| builder.build());
+        let virtual_device = match virtual_device {
+            Ok(virtual_device) => virtual_device,
+            Err(e) => {
+                error!(target: "hid", "Failed to create uinput device for emulated fn-lock: {}", e);
+                return None;
+            }
+        };
+
+        if let Err(e) = device.grab() {
+            error!(target: "hid", "Failed to grab input device for emulated fn-lock: {}", e);
+            return None;
+        }
+        info!(target: "hid", device = device.name().unwrap_or("unknown device"), "Grabbed device for emulated fn-lock");
+
+        Some(EmulatedFnLock {
+            virtual_device,
+            pressed: HashMap::new(),
+            pending: Vec::new(),
+        })
+    }
+
+    // forward an event from the grabbed device, swapping function and media keys if fn-lock is on.
+    // everything else is passed through unchanged
+    pub fn forward(&mut self, ev: &InputEvent, fn_lock: bool) {
+        match ev.event_type() {
+            EventType::KEY => {
+                let code = match ev.value() {
+                    1 => {
+                        let code = if fn_lock { swapped_code(ev.code()) } else { ev.code() };
+                        self.pressed.insert(ev.code(), code);
+                        code
+                    }
+                    0 => self.pressed.remove(&ev.code()).unwrap_or(ev.code()),
+                    _ => *self.pressed.get(&ev.code()).unwrap_or(&ev.code()), // autorepeat
+                };
+                self.pending.push(InputEvent::new(EventType::KEY.0, code, ev.value()));
+            }
+            // SYN_DROPPED and friends are for the grabbed device's own buffer
+            EventType::SYNCHRONIZATION if ev.code() != SynchronizationCode::SYN_REPORT.0 => {}
+            EventType::SYNCHRONIZATION => {
+                if !self.pending.is_empty() {
+                    // emit() terminates the batch with its own SYN_REPORT
+                    if let Err(e) = self.virtual_device.emit(&self.pending) {
+                        error!(target: "hid", "Error writing to uinput device: {}", e);
+                    }
+                    self.pending.clear();
+                }
+            }
+            _ => self.pending.push(*ev),
+        }
+    }
+}
+
+fn swapped_code(code: u16) -> u16 {
+    for (fkey, media) in EMULATED_SWAPS.iter() {
+        if fkey.code() == code {
+            return media.code();
+        }
+        if media.code() == code {
+            return fkey.code();
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn finds_the_first_existing_attribute() {
+        let sysfs = TempDir::new("fn-lock");
+        sysfs.write("bus/platform/devices/asus-nb-wmi/fn_lock", "1\n");
+        sysfs.write("class/firmware-attributes/asus-armoury/attributes/fn_lock/current_value", "1\n");
+        assert_eq!(
+            find_sysfs_fn_lock(sysfs.path()),
+            Some(format!("{}/bus/platform/devices/asus-nb-wmi/fn_lock", sysfs.path()))
+        );
+    }
+
+    #[test]
+    fn finds_the_armoury_attribute() {
+        let sysfs = TempDir::new("fn-lock");
+        sysfs.write("class/firmware-attributes/asus-armoury/attributes/fn_lock/current_value", "0\n");
+        assert_eq!(
+            find_sysfs_fn_lock(sysfs.path()),
+            Some(format!("{}/class/firmware-attributes/asus-armoury/attributes/fn_lock/current_value", sysfs.path()))
+        );
+    }
+
+    #[test]
+    fn no_attribute_without_fn_lock() {
+        let sysfs = TempDir::new("fn-lock");
+        // asus-nb-wmi loaded on a model without fn-lock support
+        sysfs.write("devices/platform/asus-nb-wmi/throttle_thermal_policy", "0\n");
+        assert_eq!(find_sysfs_fn_lock(sysfs.path()), None);
+    }
+
+    #[test]
+    fn directories_are_not_attributes() {
+        let sysfs = TempDir::new("fn-lock");
+        sysfs.create_dir("devices/platform/asus-nb-wmi/fn_lock");
+        assert_eq!(find_sysfs_fn_lock(sysfs.path()), None);
+    }
+}
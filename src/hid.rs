@@ -1,23 +1,49 @@
 use std::ffi::CString;
-use evdev::KeyCode;
-use hidapi::HidApi;
+use evdev::{KeyCode, SwitchCode};
+use std::sync::Mutex;
+use hidapi::{HidApi, HidDevice};
+use crate::fn_lock::EMULATED_DEVICE_NAME;
+use crate::privileges;
+use tracing::{error, info};
+
+pub static ASUS_IDS: &str = "0B05:19B6";
+pub static INPUT_ROOT: &str = "/sys/class/input";
+// the vendor collection carrying the hotkey reports and the fn-lock feature report
+static VENDOR_USAGE_PAGE: u16 = 0xff31;
+static VENDOR_USAGE: u16 = 0x76;
+static VENDOR_REPORT_ID: u8 = 0x5a;
+static HID_DEVICES_PATH: &str = "/sys/bus/hid/devices";
 
 #[derive(Clone)]
 pub struct HidDeviceInfo {
     pub hid_id: u32,
+    pub bus_path: String,
     pub possible_event_paths: Vec<String>,
     pub hidraw_device_path: String,
 }
 
-pub fn toggle_fn_lock(hid_path: &String, new_state: bool) {
-    let c_string = CString::new(hid_path.clone()).expect("CString::new failed");
-    let c_str = c_string.as_c_str();
+// the hidraw device used for fn-lock, kept open so it still works after dropping privileges
+static FN_LOCK_DEVICE: Mutex<Option<(String, HidDevice)>> = Mutex::new(None);
 
-    // Open the HID device at the specified path
-    let device = HidApi::new()
-        .expect("HidApi::new failed");
-    let handle = device.open_path(c_str)
-        .expect("Failed to open HID device");
+// returns true if the feature report was accepted by the device
+pub fn toggle_fn_lock(hid_path: &String, new_state: bool) -> bool {
+    let mut fn_lock_device = FN_LOCK_DEVICE.lock().expect("Failed to lock fn-lock device");
+    if fn_lock_device.as_ref().is_none_or(|(path, _)| path != hid_path) {
+        let c_string = CString::new(hid_path.clone()).expect("CString::new failed");
+        let c_str = c_string.as_c_str();
+
+        // Open the HID device at the specified path
+        let device = HidApi::new()
+            .expect("HidApi::new failed");
+        match device.open_path(c_str) {
+            Ok(handle) => *fn_lock_device = Some((hid_path.clone(), handle)),
+            Err(e) => {
+                error!(target: "hid", device = %hid_path, "Failed to open HID device: {}", e);
+                return false;
+            }
+        }
+    }
+    let (_, handle) = fn_lock_device.as_ref().unwrap();
 
     // Create a feature report to send
     let mut feature_report: [u8; 63] = [
@@ -31,39 +57,379 @@ pub fn toggle_fn_lock(hid_path: &String, new_state: bool) {
 
     // Send the feature report
     match handle.send_feature_report(&feature_report) {
-        Ok(_) => println!("Fn-Lock command sent successfully!"),
-        Err(e) => eprintln!("Error sending feature report: {}", e),
+        Ok(_) => {
+            info!(target: "hid", device = %hid_path, fn_lock = new_state, "Fn-Lock command sent");
+            true
+        }
+        Err(e) => {
+            error!(target: "hid", device = %hid_path, "Error sending feature report: {}", e);
+            false
+        }
     }
 }
 
 fn get_bus_path(vid_pid: &str) -> String {
-    let syspath = "/sys/bus/hid/devices";
-    // read in directory to find the target
-    let entries = std::fs::read_dir(syspath)
+    find_bus_path(vid_pid).unwrap_or_else(|| panic!("No matching HID device found"))
+}
+
+// sysfs path of the keyboard's vendor hid interface, None if it isn't connected
+pub fn find_vendor_interface() -> Option<String> {
+    find_bus_path(ASUS_IDS)
+}
+
+// /dev node of the hidraw device for a hid interface
+pub fn hidraw_node(bus_path: &str) -> Option<String> {
+    std::fs::read_dir(format!("{}/hidraw", bus_path)).into_iter().flatten().flatten()
+        .map(|entry| format!("/dev/{}", entry.file_name().to_string_lossy()))
+        .next()
+}
+
+// /dev/input nodes of every input device the driver created for a hid interface
+pub fn event_nodes(bus_path: &str) -> Vec<String> {
+    let inputs = std::fs::read_dir(format!("{}/input", bus_path)).into_iter().flatten().flatten();
+    inputs
+        .flat_map(|input| std::fs::read_dir(input.path()).into_iter().flatten().flatten())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("event"))
+        .map(|name| format!("/dev/input/{}", name))
+        .collect()
+}
+
+// event nodes of every interface of the keyboard, e.g. its keyboard and consumer control devices
+pub fn keyboard_event_nodes() -> Vec<String> {
+    matching_interfaces(ASUS_IDS).iter().flat_map(|bus_path| event_nodes(bus_path)).collect()
+}
+
+// event nodes of the input devices reporting any of the switches, e.g. the lid switch.  those are
+// separate acpi devices without keys, found by their capabilities in sysfs
+pub fn switch_event_nodes(input_root: &str, switches: &[SwitchCode]) -> Vec<String> {
+    let mut nodes: Vec<String> = Vec::new();
+    if switches.is_empty() {
+        return nodes;
+    }
+    for entry in std::fs::read_dir(input_root).into_iter().flatten().flatten() {
+        let Ok(capabilities) = std::fs::read_to_string(entry.path().join("capabilities/sw")) else {
+            continue;
+        };
+        // space separated hex words, lowest bits last
+        let lowest = capabilities.split_whitespace().last()
+            .and_then(|word| u64::from_str_radix(word, 16).ok())
+            .unwrap_or(0);
+        if !switches.iter().any(|switch| switch.0 < 64 && lowest & (1 << switch.0) != 0) {
+            continue;
+        }
+        let events = std::fs::read_dir(entry.path()).into_iter().flatten().flatten()
+            .map(|child| child.file_name().to_string_lossy().to_string())
+            .filter(|name| name.starts_with("event"))
+            .map(|name| format!("/dev/input/{}", name));
+        nodes.extend(events);
+    }
+    nodes.sort();
+    nodes
+}
+
+// current state of a switch, read from the first device reporting it.  None if there is none
+pub fn switch_state(input_root: &str, switch: SwitchCode) -> Option<bool> {
+    switch_event_nodes(input_root, &[switch]).iter().find_map(|path| {
+        let device = privileges::open_event_device(path).ok()?;
+        let state = device.get_switch_state().ok()?;
+        Some(state.contains(switch))
+    })
+}
+
+// the interface whose report descriptor has the vendor collection
+fn find_bus_path(vid_pid: &str) -> Option<String> {
+    matching_interfaces(vid_pid).into_iter().find(|bus_path| {
+        read_report_descriptor(bus_path).is_ok_and(|descriptor| descriptor.vendor_collection().is_some())
+    })
+}
+
+// sysfs paths of every hid interface of a device, sorted
+fn matching_interfaces(vid_pid: &str) -> Vec<String> {
+    let entries = std::fs::read_dir(HID_DEVICES_PATH)
         .expect("Failed to read /sys/bus/hid/devices directory");
+    let mut interfaces: Vec<String> = entries.flatten()
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.to_uppercase().contains(vid_pid))
+        .map(|name| format!("{}/{}", HID_DEVICES_PATH, name))
+        .collect();
+    interfaces.sort();
+    interfaces
+}
+
+fn read_report_descriptor(bus_path: &str) -> Result<ReportDescriptor, String> {
+    let bytes = std::fs::read(format!("{}/report_descriptor", bus_path))
+        .map_err(|e| format!("Unable to read report descriptor: {}", e))?;
+    parse_report_descriptor(&bytes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportKind {
+    Input,
+    Output,
+    Feature,
+}
+
+impl ReportKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportKind::Input => "input",
+            ReportKind::Output => "output",
+            ReportKind::Feature => "feature",
+        }
+    }
+}
+
+// every field of one report within a top level collection
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub kind: ReportKind,
+    // 0 when the device doesn't number its reports
+    pub report_id: u8,
+    pub bits: u32,
+    // extended usages (page << 16 | usage) as inclusive ranges
+    pub usages: Vec<(u32, u32)>,
+}
+
+impl Report {
+    // size on the wire, including the report id
+    pub fn byte_len(&self) -> usize {
+        self.bits.div_ceil(8) as usize + if self.report_id != 0 { 1 } else { 0 }
+    }
+}
+
+// a top level collection.  fields of nested collections are counted in their top level one
+#[derive(Debug, Clone)]
+pub struct Collection {
+    pub usage_page: u16,
+    pub usage: u16,
+    pub reports: Vec<Report>,
+}
 
-    for entry in entries {
-        let entry = entry.expect("Failed to read entry");
-        let file_name = entry.file_name();
-        let file_name_str = file_name.to_str()
-            .expect("Failed to convert file name to string");
-        if file_name_str.contains(vid_pid) {
-            let full_path = format!("{}/{}", syspath, file_name_str);
-            // now check the report_descriptor file
-            let report_descriptor_path = format!("{}/report_descriptor", full_path);
-            let report_descriptor = std::fs::read(report_descriptor_path)
-                .expect("Failed to read report_descriptor file");
-            let exp_bytes = [0x06, 0x31, 0xff, 0x09, 0x76, 0xa1, 0x01, 0x85, 0x5a].to_vec();
-            // check if report_descriptor contains expected_descriptor bytes at any position
-            let found = report_descriptor
-                .windows(exp_bytes.len())
-                .any(|window| window == exp_bytes.as_slice());
-            if found {
-                return full_path;
+#[derive(Debug, Clone, Default)]
+pub struct ReportDescriptor {
+    pub collections: Vec<Collection>,
+}
+
+impl ReportDescriptor {
+    // the collection with the hotkey input reports
+    pub fn vendor_collection(&self) -> Option<&Collection> {
+        self.collections.iter().find(|collection| {
+            collection.usage_page == VENDOR_USAGE_PAGE
+                && collection.usage == VENDOR_USAGE
+                && collection.reports.iter()
+                    .any(|report| report.kind == ReportKind::Input && report.report_id == VENDOR_REPORT_ID)
+        })
+    }
+}
+
+// global items, saved and restored by push and pop
+#[derive(Debug, Clone, Copy, Default)]
+struct GlobalState {
+    usage_page: u16,
+    report_id: u8,
+    report_size: u32,
+    report_count: u32,
+}
+
+// short items only, long items are skipped.  see "Device Class Definition for HID" 6.2.2
+pub fn parse_report_descriptor(bytes: &[u8]) -> Result<ReportDescriptor, String> {
+    let mut descriptor = ReportDescriptor::default();
+    let mut global = GlobalState::default();
+    let mut global_stack: Vec<GlobalState> = Vec::new();
+    // local items, cleared after every main item
+    let mut usages: Vec<(u32, u32)> = Vec::new();
+    let mut usage_minimum: Option<u32> = None;
+    let mut depth = 0usize;
+
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let prefix = bytes[offset];
+        if prefix == 0xfe {
+            let size = *bytes.get(offset + 1).ok_or("Truncated long item")? as usize;
+            offset += 3 + size;
+            if offset > bytes.len() {
+                return Err("Truncated long item".to_string());
             }
+            continue;
         }
+        let size = match prefix & 0x03 {
+            3 => 4,
+            size => size as usize,
+        };
+        let data = bytes.get(offset + 1..offset + 1 + size)
+            .ok_or_else(|| format!("Truncated item {:#04x} at offset {}", prefix, offset))?;
+        let value = data.iter().rev().fold(0u32, |value, byte| (value << 8) | *byte as u32);
+        // 4 byte usages carry their own page
+        let extended = |value: u32| if size == 4 { value } else { ((global.usage_page as u32) << 16) | value };
+        offset += 1 + size;
+
+        let tag = prefix >> 4;
+        match (prefix >> 2) & 0x03 {
+            // main items
+            0 => {
+                match tag {
+                    0x8 | 0x9 | 0xb => {
+                        let kind = match tag {
+                            0x8 => ReportKind::Input,
+                            0x9 => ReportKind::Output,
+                            _ => ReportKind::Feature,
+                        };
+                        if let Some(collection) = descriptor.collections.last_mut().filter(|_| depth > 0) {
+                            add_field(collection, kind, &global, &usages)?;
+                        }
+                    }
+                    0xa => {
+                        if depth == 0 {
+                            let usage = usages.first().map(|(start, _)| *start).unwrap_or(0);
+                            descriptor.collections.push(Collection {
+                                usage_page: (usage >> 16) as u16,
+                                usage: usage as u16,
+                                reports: Vec::new(),
+                            });
+                        }
+                        depth += 1;
+                    }
+                    0xc => {
+                        depth = depth.checked_sub(1)
+                            .ok_or_else(|| format!("End collection without a collection at offset {}", offset - 1 - size))?;
+                    }
+                    _ => {}
+                }
+                usages.clear();
+                usage_minimum = None;
+            }
+            // global items
+            1 => match tag {
+                0x0 => global.usage_page = value as u16,
+                0x7 => global.report_size = value,
+                0x8 => global.report_id = value as u8,
+                0x9 => global.report_count = value,
+                0xa => global_stack.push(global),
+                0xb => global = global_stack.pop().ok_or("Pop without push")?,
+                _ => {}
+            },
+            // local items
+            2 => match tag {
+                0x0 => add_usages(&mut usages, extended(value), extended(value)),
+                0x1 => usage_minimum = Some(extended(value)),
+                0x2 => {
+                    let start = usage_minimum.take().unwrap_or(extended(value));
+                    add_usages(&mut usages, start, extended(value));
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err("Report descriptor ends inside a collection".to_string());
+    }
+    Ok(descriptor)
+}
+
+// consecutive usages are merged into one range
+fn add_usages(usages: &mut Vec<(u32, u32)>, start: u32, end: u32) {
+    if let Some(last) = usages.last_mut() {
+        if last.1.checked_add(1) == Some(start) {
+            last.1 = end;
+            return;
+        }
+    }
+    usages.push((start, end));
+}
+
+// errors if the report grows past u32::MAX bits, which only a malformed descriptor can do
+fn add_field(collection: &mut Collection, kind: ReportKind, global: &GlobalState, usages: &[(u32, u32)]) -> Result<(), String> {
+    let index = collection.reports.iter()
+        .position(|report| report.kind == kind && report.report_id == global.report_id);
+    let report = match index {
+        Some(index) => &mut collection.reports[index],
+        None => {
+            collection.reports.push(Report { kind, report_id: global.report_id, bits: 0, usages: Vec::new() });
+            collection.reports.last_mut().unwrap()
+        }
+    };
+    report.bits = global.report_size.checked_mul(global.report_count)
+        .and_then(|bits| report.bits.checked_add(bits))
+        .ok_or_else(|| format!("Report {:#04x} is too long", global.report_id))?;
+    for (start, end) in usages {
+        add_usages(&mut report.usages, *start, *end);
+    }
+    Ok(())
+}
+
+fn usage_page_name(page: u16) -> String {
+    match page {
+        0x01 => "generic desktop".to_string(),
+        0x07 => "keyboard".to_string(),
+        0x08 => "leds".to_string(),
+        0x0c => "consumer".to_string(),
+        0x0d => "digitizer".to_string(),
+        0xff00..=0xffff => format!("vendor {:#06x}", page),
+        _ => format!("{:#04x}", page),
     }
-    panic!("No matching HID device found");
+}
+
+fn format_usages(usages: &[(u32, u32)]) -> String {
+    if usages.is_empty() {
+        return "none".to_string();
+    }
+    usages.iter()
+        .map(|(start, end)| {
+            if start == end {
+                format!("{:#06x}:{:#04x}", start >> 16, start & 0xffff)
+            } else {
+                format!("{:#06x}:{:#04x}-{:#04x}", start >> 16, start & 0xffff, end & 0xffff)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn print_report_descriptor(descriptor: &ReportDescriptor) {
+    for collection in &descriptor.collections {
+        println!("  collection {}, usage {:#04x}", usage_page_name(collection.usage_page), collection.usage);
+        for report in &collection.reports {
+            let report_id = if report.report_id == 0 { "without id".to_string() } else { format!("{:#04x}", report.report_id) };
+            println!(
+                "    {} report {}: {} bytes, usages {}",
+                report.kind.name(),
+                report_id,
+                report.byte_len(),
+                format_usages(&report.usages)
+            );
+        }
+    }
+}
+
+// describe-device [report_descriptor]: the parsed report descriptors of the keyboard's hid
+// interfaces, or of a descriptor saved from /sys/bus/hid/devices/*/report_descriptor
+pub fn describe_device(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = args.first() {
+        let bytes = std::fs::read(path).map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        let descriptor = parse_report_descriptor(&bytes)?;
+        println!("{}{}", path, if descriptor.vendor_collection().is_some() { " (vendor interface)" } else { "" });
+        print_report_descriptor(&descriptor);
+        return Ok(());
+    }
+
+    let interfaces = matching_interfaces(ASUS_IDS);
+    if interfaces.is_empty() {
+        return Err(format!("No {} HID device found", ASUS_IDS).into());
+    }
+    for bus_path in interfaces {
+        let hidraw = hidraw_node(&bus_path).unwrap_or("no hidraw node".to_string());
+        match read_report_descriptor(&bus_path) {
+            Ok(descriptor) => {
+                let vendor = if descriptor.vendor_collection().is_some() { ", vendor interface" } else { "" };
+                println!("{} ({}{})", bus_path, hidraw, vendor);
+                print_report_descriptor(&descriptor);
+            }
+            Err(e) => println!("{} ({}): {}", bus_path, hidraw, e),
+        }
+    }
+    Ok(())
 }
 
 fn parse_hid_id(bus_path: String) -> u32 {
@@ -76,28 +442,16 @@ fn parse_hid_id(bus_path: String) -> u32 {
 }
 
 pub fn get_hardware_info(target_key_codes: &Vec<KeyCode>) -> HidDeviceInfo{
-    let asus_ids = "0B05:19B6";
-    let asus_bus_path = get_bus_path(asus_ids);
+    let asus_bus_path = get_bus_path(ASUS_IDS);
 
     HidDeviceInfo {
-        hid_id: parse_hid_id(asus_bus_path),
+        hid_id: parse_hid_id(asus_bus_path.clone()),
         possible_event_paths: get_possible_event_paths(target_key_codes),
-        hidraw_device_path: get_hidraw_path(),
+        hidraw_device_path: hidraw_node(&asus_bus_path).unwrap_or_else(|| panic!("No hidraw device for the vendor interface")),
+        bus_path: asus_bus_path,
     }
 }
 
-fn get_hidraw_path() -> String{
-    let mut hid = HidApi::new().expect("HidApi::new failed");
-    hid.reset_devices().expect("HidApi::new failed");
-    hid.add_devices(0x0b05, 0x19b6).expect("Failed to add devices");
-    for device in hid.device_list() {
-        if device.usage() == 0x76 && device.usage_page() == 0xff31 {
-            return device.path().to_str().unwrap().to_string();
-        }
-    }
-    panic!("No matching HID device found");
-}
-
 pub fn get_possible_event_paths(target_key_codes: &Vec<KeyCode>) -> Vec<String> {
     let mut paths: Vec<String> = Vec::new();
     if target_key_codes.len() == 0 {
@@ -114,12 +468,20 @@ pub fn get_possible_event_paths(target_key_codes: &Vec<KeyCode>) -> Vec<String>
         });
         if !found.is_none() {
             let path = found.unwrap().into_string().unwrap();
-            // now check if this event device has the target key codes
-            let input_dev_res = evdev::Device::open(&path);
+            // mice and joysticks have nodes in the input subsystem too
+            if !path.starts_with("/dev/input/event") {
+                continue;
+            }
+            // now check if this event device has the target key codes.  opened through the root
+            // helper once privileges are dropped
+            let input_dev_res = privileges::open_event_device(&path);
             if input_dev_res.is_err() {
                 continue;
             }
             let input_dev = input_dev_res.expect("Failed to open input device");
+            if input_dev.name() == Some(EMULATED_DEVICE_NAME) {
+                continue; // our own uinput device
+            }
             if input_dev.supported_keys().is_none() {
                 continue;
             }
@@ -138,4 +500,87 @@ pub fn get_possible_event_paths(target_key_codes: &Vec<KeyCode>) -> Vec<String>
     }
 
     paths // return value
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::uhid::SYNTHETIC_REPORT_DESCRIPTOR;
+
+    #[test]
+    fn parses_the_synthetic_vendor_descriptor() {
+        let descriptor = parse_report_descriptor(&SYNTHETIC_REPORT_DESCRIPTOR).unwrap();
+        assert_eq!(descriptor.collections.len(), 1);
+        let collection = descriptor.vendor_collection().expect("No vendor collection");
+        assert_eq!((collection.usage_page, collection.usage), (0xff31, 0x76));
+        let input = collection.reports.iter().find(|report| report.kind == ReportKind::Input).unwrap();
+        assert_eq!((input.report_id, input.bits, input.byte_len()), (0x5a, 40, 6));
+        assert_eq!(input.usages, vec![(0xff31_0000, 0xff31_00ff)]);
+        let feature = collection.reports.iter().find(|report| report.kind == ReportKind::Feature).unwrap();
+        assert_eq!((feature.report_id, feature.byte_len()), (0x5a, 64));
+    }
+
+    #[test]
+    fn rejects_truncated_descriptors() {
+        for len in [1, 4, 8, 13, SYNTHETIC_REPORT_DESCRIPTOR.len() - 1] {
+            assert!(parse_report_descriptor(&SYNTHETIC_REPORT_DESCRIPTOR[..len]).is_err(), "accepted {} bytes", len);
+        }
+        // report size item missing its data byte
+        assert!(parse_report_descriptor(&[0x75]).is_err());
+        assert!(parse_report_descriptor(&[0xc0]).is_err());
+        assert!(parse_report_descriptor(&[0xb4]).is_err());
+    }
+
+    #[test]
+    fn rejects_reports_past_u32_bits() {
+        let descriptor = [
+            0x06, 0x31, 0xff,                   // usage page (vendor 0xff31)
+            0x09, 0x76,                         // usage (0x76)
+            0xa1, 0x01,                         // collection (application)
+            0x77, 0xff, 0xff, 0xff, 0xff,       //   report size (u32::MAX)
+            0x97, 0x02, 0x00, 0x00, 0x00,       //   report count (2)
+            0x81, 0x00,                         //   input
+            0xc0,                               // end collection
+        ];
+        assert!(parse_report_descriptor(&descriptor).is_err());
+        // each field fits, their sum doesn't
+        let descriptor = [
+            0x06, 0x31, 0xff, 0x09, 0x76, 0xa1, 0x01,
+            0x77, 0x00, 0x00, 0x00, 0x80,       //   report size (2^31)
+            0x95, 0x01,                         //   report count (1)
+            0x81, 0x00, 0x81, 0x00,             //   input, input
+            0xc0,
+        ];
+        assert!(parse_report_descriptor(&descriptor).is_err());
+    }
+
+    #[test]
+    fn skips_long_items() {
+        let mut descriptor = vec![0xfe, 0x03, 0x10, 0xaa, 0xbb, 0xcc];
+        descriptor.extend_from_slice(&SYNTHETIC_REPORT_DESCRIPTOR);
+        let parsed = parse_report_descriptor(&descriptor).unwrap();
+        assert!(parsed.vendor_collection().is_some());
+        // data size past the end
+        assert!(parse_report_descriptor(&[0xfe, 0x04, 0x10, 0xaa]).is_err());
+        assert!(parse_report_descriptor(&[0xfe]).is_err());
+    }
+
+    #[test]
+    fn finds_switch_event_nodes() {
+        let input = TempDir::new("input");
+        // lid switch, tablet mode switch and a keyboard
+        input.write("input3/capabilities/sw", "1\n");
+        input.create_dir("input3/event3");
+        input.write("input7/capabilities/sw", "0 2\n");
+        input.create_dir("input7/event7");
+        input.write("input9/capabilities/sw", "0\n");
+        input.create_dir("input9/event9");
+        input.create_dir("input9/mouse0");
+        assert_eq!(switch_event_nodes(input.path(), &[SwitchCode::SW_LID]), vec!["/dev/input/event3".to_string()]);
+        assert_eq!(
+            switch_event_nodes(input.path(), &[SwitchCode::SW_LID, SwitchCode::SW_TABLET_MODE]),
+            vec!["/dev/input/event3".to_string(), "/dev/input/event7".to_string()]
+        );
+        assert!(switch_event_nodes(input.path(), &[]).is_empty());
+    }
+}
@@ -0,0 +1,386 @@
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::os::fd::AsRawFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use evdev::uinput::VirtualDevice;
+use evdev::{AttributeSet, Device, EventType, InputEvent, KeyCode, MiscCode};
+use crate::apkt_config::BpfConfig;
+use crate::hid::hidraw_node;
+use crate::privileges;
+use crate::remap_model::{log_entry, RemapTable};
+use crate::sd_notify::{heartbeat, heartbeat_idle, Heartbeat};
+use tracing::{debug, error, info, warn};
+
+// remaps without HID-BPF: the hotkey reports are read from hidraw, remapped with the same model and
+// sent through uinput.  hid-asus' own input device for the vendor interface is grabbed so the
+// original keys don't come through twice
+
+static FALLBACK_DEVICE_NAME: &str = "asus-px-keyboard-tool remap fallback";
+static UINPUT_PATH: &str = "/dev/uinput";
+// hid-input reports the usage as MSC_SCAN, usage page 0xff31 in the high half
+static VENDOR_USAGE_PAGE: u32 = 0xff31_0000;
+
+// asus_input_mapping in drivers/hid/hid-asus.c, for usage page 0xff31
+static ASUS_KEYMAP: [(u8, KeyCode); 29] = [
+    (0x10, KeyCode::KEY_BRIGHTNESSDOWN),
+    (0x20, KeyCode::KEY_BRIGHTNESSUP),
+    (0x35, KeyCode::KEY_DISPLAY_OFF),
+    (0x38, KeyCode::KEY_PROG1), // rog key
+    (0x4b, KeyCode::KEY_F14), // arrows/page up/down toggle
+    (0x4e, KeyCode::KEY_FN_ESC),
+    (0x5c, KeyCode::KEY_PROG3), // fn+v
+    (0x6a, KeyCode::KEY_F13), // screenpad toggle
+    (0x6b, KeyCode::KEY_F21), // touchpad toggle
+    (0x6c, KeyCode::KEY_SLEEP),
+    (0x7c, KeyCode::KEY_MICMUTE),
+    (0x7e, KeyCode::new(0x249)), // KEY_EMOJI_PICKER, missing from evdev's constants
+    (0x82, KeyCode::KEY_CAMERA),
+    (0x88, KeyCode::KEY_RFKILL),
+    (0x8b, KeyCode::KEY_PROG1), // fn+f12 on older models
+    (0x92, KeyCode::KEY_CALC),
+    (0x99, KeyCode::KEY_PROG4), // fan profile
+    (0xa5, KeyCode::KEY_F15), // fn+f6, extra display
+    (0xa6, KeyCode::KEY_F16),
+    (0xa7, KeyCode::KEY_F17),
+    (0xa8, KeyCode::KEY_F18),
+    (0xae, KeyCode::KEY_PROG4), // fan profile on newer models
+    (0xb2, KeyCode::KEY_PROG2), // armoury crate
+    (0xb3, KeyCode::KEY_PROG3), // aura
+    (0xb5, KeyCode::KEY_CALC),
+    (0xba, KeyCode::KEY_PROG2), // fn+c
+    (0xc4, KeyCode::KEY_KBDILLUMUP),
+    (0xc5, KeyCode::KEY_KBDILLUMDOWN),
+    (0xc7, KeyCode::KEY_KBDILLUMTOGGLE),
+];
+
+static REMAP_TABLE: Mutex<Option<RemapTable>> = Mutex::new(None);
+static VIRTUAL_DEVICE: Mutex<Option<VirtualDevice>> = Mutex::new(None);
+// hid-asus' event devices for the vendor interface, kept open so they stay grabbed
+static GRABBED_DEVICES: Mutex<Vec<Device>> = Mutex::new(Vec::new());
+static READ_THREAD: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+static STOP_READING: AtomicBool = AtomicBool::new(false);
+
+pub fn is_usable(hidraw_path: &str) -> bool {
+    privileges::can_access(hidraw_path, false) && privileges::can_access(UINPUT_PATH, true)
+}
+
+fn keycode_for(scancode: u8) -> Option<KeyCode> {
+    ASUS_KEYMAP.iter()
+        .find(|(code, _)| *code == scancode)
+        .map(|(_, key)| *key)
+}
+
+// start reading hotkey reports from hidraw.  returns the event device node of the uinput device
+// the keys are sent from, None if the fallback couldn't be set up
+pub fn start_hidraw_remap(hidraw_path: &str, bus_path: &str, config: &BpfConfig) -> Option<String> {
+    if config.rules.iter().any(|rule| !config.report_ids.contains(&rule.report_id)) {
+        warn!(target: "bpf", "Rules for reports not in bpf.report_ids need HID-BPF, they are ignored");
+    }
+    let table = match RemapTable::new(config) {
+        Ok(table) => table,
+        Err(e) => {
+            error!(target: "bpf", "{}", e);
+            return None;
+        }
+    };
+    let hidraw = match File::open(hidraw_path) {
+        Ok(hidraw) => hidraw,
+        Err(e) => {
+            error!(target: "bpf", device = %hidraw_path, "Failed to open hidraw device: {}", e);
+            return None;
+        }
+    };
+
+    // KEY_UNKNOWN and MSC_SCAN carry the scancodes hid-asus has no key for, hwdb can map them
+    let mut keys = AttributeSet::<KeyCode>::new();
+    for (_, key) in ASUS_KEYMAP.iter() {
+        keys.insert(*key);
+    }
+    keys.insert(KeyCode::KEY_UNKNOWN);
+    let mut misc = AttributeSet::<MiscCode>::new();
+    misc.insert(MiscCode::MSC_SCAN);
+    let virtual_device = VirtualDevice::builder()
+        .map(|builder| builder.name(FALLBACK_DEVICE_NAME))
+        .and_then(|builder| builder.with_keys(&keys))
+        .and_then(|builder| builder.with_msc(&misc))
+        .and_then(|builder| builder.build());
+    let mut virtual_device = match virtual_device {
+        Ok(virtual_device) => virtual_device,
+        Err(e) => {
+            error!(target: "bpf", "Failed to create uinput device for remapping: {}", e);
+            return None;
+        }
+    };
+    let device_node = virtual_device.enumerate_dev_nodes_blocking().ok()
+        .and_then(|mut nodes| nodes.next())
+        .and_then(|node| node.ok())
+        .map(|node| node.to_string_lossy().to_string());
+
+    grab_vendor_input_devices(bus_path);
+    *REMAP_TABLE.lock().expect("Failed to lock remap table") = Some(table);
+    *VIRTUAL_DEVICE.lock().expect("Failed to lock uinput device") = Some(virtual_device);
+    *READ_THREAD.lock().expect("Failed to lock hidraw read thread") = Some(spawn_reader(hidraw));
+    info!(target: "bpf", device = %hidraw_path, "Remapping through hidraw and uinput");
+    device_node
+}
+
+fn spawn_reader(mut hidraw: File) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut pressed: Option<KeyCode> = None;
+        // debounce windows are measured from here
+        let started = Instant::now();
+        let mut report = [0u8; 64];
+        while !STOP_READING.load(Ordering::Relaxed) {
+            heartbeat(Heartbeat::Bpf);
+            // wait at most a second so stop_hidraw_remap and the watchdog aren't kept waiting
+            let mut poll_fd = libc::pollfd { fd: hidraw.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+            let ready = unsafe { libc::poll(&mut poll_fd, 1, 1000) };
+            if ready <= 0 {
+                continue;
+            }
+            let len = match hidraw.read(&mut report) {
+                Ok(len) => len,
+                Err(e) if e.raw_os_error() == Some(libc::ENODEV) => {
+                    // unplugged or rebound, the hotplug path calls reattach once it's back
+                    info!(target: "bpf", "Hidraw device removed, waiting for it to come back");
+                    emit(&key_events(0, &mut pressed));
+                    heartbeat_idle(Heartbeat::Bpf);
+                    return;
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    warn!(target: "bpf", "Error reading hidraw device, retrying: {}", e);
+                    std::thread::sleep(Duration::from_secs(1));
+                    continue;
+                }
+            };
+            let report = &mut report[..len];
+            if report.len() < 2 {
+                continue;
+            }
+
+            let mut remap_table = REMAP_TABLE.lock().expect("Failed to lock remap table");
+            let Some(table) = remap_table.as_mut() else {
+                continue;
+            };
+            if !table.is_hotkey_report(report[0]) {
+                continue;
+            }
+            let entry = table.apply(report, started.elapsed().as_nanos() as u64);
+            drop(remap_table);
+            if let Some(entry) = entry {
+                log_entry(&entry);
+                if entry.reason.is_some() {
+                    continue; // dropped, like the bpf program does
+                }
+            }
+            emit(&key_events(report[1], &mut pressed));
+        }
+    })
+}
+
+fn emit(events: &[InputEvent]) {
+    if events.is_empty() {
+        return;
+    }
+    let mut virtual_device = VIRTUAL_DEVICE.lock().expect("Failed to lock uinput device");
+    if let Some(virtual_device) = virtual_device.as_mut() {
+        if let Err(e) = virtual_device.emit(events) {
+            error!(target: "bpf", "Error writing to uinput device: {}", e);
+        }
+    }
+}
+
+// the reports work like a one key array: a scancode is a press, 0 releases whatever was pressed.
+// scancodes hid-asus has no key for are sent unchanged as MSC_SCAN with KEY_UNKNOWN
+fn key_events(scancode: u8, pressed: &mut Option<KeyCode>) -> Vec<InputEvent> {
+    let mut events = Vec::new();
+    if let Some(key) = pressed.take() {
+        events.push(InputEvent::new(EventType::KEY.0, key.code(), 0));
+    }
+    if scancode != 0 {
+        let key = keycode_for(scancode).unwrap_or_else(|| {
+            debug!(target: "bpf", scancode = %format!("{:#04x}", scancode), "No keycode for scancode, sending KEY_UNKNOWN");
+            KeyCode::KEY_UNKNOWN
+        });
+        events.push(InputEvent::new(EventType::MISC.0, MiscCode::MSC_SCAN.0, (VENDOR_USAGE_PAGE | scancode as u32) as i32));
+        events.push(InputEvent::new(EventType::KEY.0, key.code(), 1));
+        *pressed = Some(key);
+    }
+    events
+}
+
+// true for the input device of the vendor collection.  hid-input gives every collection of the
+// interface its own input device with the interface's phys, and names it after the interface with
+// a suffix like " Keyboard" or " Consumer Control", except for vendor collections.  grabbing the
+// others would take the built-in keyboard away
+fn is_vendor_input_device(input_dir: &Path, hid_name: &str, hid_phys: &str) -> bool {
+    let read = |name: &str| std::fs::read_to_string(input_dir.join(name)).map(|value| value.trim_end().to_string());
+    matches!((read("name"), read("phys")), (Ok(name), Ok(phys)) if name == hid_name && phys == hid_phys)
+}
+
+// /dev/input nodes of the input devices for the vendor collection of a hid interface
+fn vendor_event_nodes(bus_path: &str) -> Vec<String> {
+    let uevent = std::fs::read_to_string(format!("{}/uevent", bus_path)).unwrap_or_default();
+    let field = |key: &str| uevent.lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+        .unwrap_or_default()
+        .to_string();
+    let (hid_name, hid_phys) = (field("HID_NAME"), field("HID_PHYS"));
+    if hid_name.is_empty() {
+        return Vec::new();
+    }
+    let inputs = std::fs::read_dir(format!("{}/input", bus_path)).into_iter().flatten().flatten();
+    inputs
+        .filter(|input| is_vendor_input_device(&input.path(), &hid_name, &hid_phys))
+        .flat_map(|input| std::fs::read_dir(input.path()).into_iter().flatten().flatten())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.starts_with("event"))
+        .map(|name| format!("/dev/input/{}", name))
+        .collect()
+}
+
+// the event node hid-asus created for the vendor collection
+fn grab_vendor_input_devices(bus_path: &str) {
+    let mut grabbed = GRABBED_DEVICES.lock().expect("Failed to lock grabbed devices");
+    grabbed.clear();
+    for path in vendor_event_nodes(bus_path) {
+        let mut device = match privileges::open_event_device(&path) {
+            Ok(device) => device,
+            Err(e) => {
+                warn!(target: "bpf", device = %path, "Failed to open hid-asus input device: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = device.grab() {
+            warn!(target: "bpf", device = %path, "Failed to grab hid-asus input device, keys may be reported twice: {}", e);
+            continue;
+        }
+        info!(target: "bpf", device = %path, "Grabbed hid-asus input device");
+        grabbed.push(device);
+    }
+    if grabbed.is_empty() {
+        warn!(target: "bpf", "No input device for the vendor collection found, keys may be reported twice");
+    }
+}
+
+// after a hotplug, read from the vendor interface's new hidraw node.  does nothing unless the
+// fallback is running and lost its device
+pub fn reattach(bus_path: &str) {
+    let mut read_thread = READ_THREAD.lock().expect("Failed to lock hidraw read thread");
+    if !read_thread.as_ref().is_some_and(|thread| thread.is_finished()) {
+        return;
+    }
+    let Some(hidraw_path) = hidraw_node(bus_path) else {
+        return;
+    };
+    let hidraw = match File::open(&hidraw_path) {
+        Ok(hidraw) => hidraw,
+        Err(e) => {
+            warn!(target: "bpf", device = %hidraw_path, "Failed to reopen hidraw device: {}", e);
+            return;
+        }
+    };
+    grab_vendor_input_devices(bus_path);
+    *read_thread = Some(spawn_reader(hidraw));
+    info!(target: "bpf", device = %hidraw_path, "Remapping through hidraw again");
+}
+
+// replace the remaps used by the fallback
+pub fn set_remaps(config: &BpfConfig) {
+    let mut remap_table = REMAP_TABLE.lock().expect("Failed to lock remap table");
+    if remap_table.is_none() {
+        return; // fallback not started
+    }
+    match RemapTable::new(config) {
+        Ok(table) => *remap_table = Some(table),
+        Err(e) => error!(target: "bpf", "Keeping the current remaps: {}", e),
+    }
+}
+
+// stop reading, then release the grabbed devices and the uinput device
+pub fn stop_hidraw_remap() {
+    STOP_READING.store(true, Ordering::Relaxed);
+    if let Some(read_thread) = READ_THREAD.lock().expect("Failed to lock hidraw read thread").take() {
+        let _ = read_thread.join();
+        info!(target: "bpf", "Hidraw remapping stopped");
+    }
+    GRABBED_DEVICES.lock().expect("Failed to lock grabbed devices").clear();
+    VIRTUAL_DEVICE.lock().expect("Failed to lock uinput device").take();
+    REMAP_TABLE.lock().expect("Failed to lock remap table").take();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn events(events: &[InputEvent]) -> Vec<(u16, u16, i32)> {
+        events.iter().map(|event| (event.event_type().0, event.code(), event.value())).collect()
+    }
+
+    #[test]
+    fn maps_known_scancodes_and_releases_them() {
+        let mut pressed = None;
+        assert_eq!(events(&key_events(0x38, &mut pressed)), vec![
+            (EventType::MISC.0, MiscCode::MSC_SCAN.0, 0xff31_0038_u32 as i32),
+            (EventType::KEY.0, KeyCode::KEY_PROG1.code(), 1),
+        ]);
+        assert_eq!(events(&key_events(0xc7, &mut pressed)), vec![
+            (EventType::KEY.0, KeyCode::KEY_PROG1.code(), 0),
+            (EventType::MISC.0, MiscCode::MSC_SCAN.0, 0xff31_00c7_u32 as i32),
+            (EventType::KEY.0, KeyCode::KEY_KBDILLUMTOGGLE.code(), 1),
+        ]);
+        assert_eq!(events(&key_events(0, &mut pressed)), vec![(EventType::KEY.0, KeyCode::KEY_KBDILLUMTOGGLE.code(), 0)]);
+        assert!(key_events(0, &mut pressed).is_empty());
+    }
+
+    #[test]
+    fn forwards_unknown_scancodes() {
+        let mut pressed = None;
+        assert_eq!(keycode_for(0xee), None);
+        assert_eq!(events(&key_events(0xee, &mut pressed)), vec![
+            (EventType::MISC.0, MiscCode::MSC_SCAN.0, 0xff31_00ee_u32 as i32),
+            (EventType::KEY.0, KeyCode::KEY_UNKNOWN.code(), 1),
+        ]);
+        assert_eq!(events(&key_events(0, &mut pressed)), vec![(EventType::KEY.0, KeyCode::KEY_UNKNOWN.code(), 0)]);
+    }
+
+    #[test]
+    fn keymap_has_no_duplicate_scancodes() {
+        for (i, (scancode, _)) in ASUS_KEYMAP.iter().enumerate() {
+            assert!(!ASUS_KEYMAP[i + 1..].iter().any(|(other, _)| other == scancode), "{:#04x} twice", scancode);
+        }
+    }
+
+    #[test]
+    fn finds_the_vendor_collections_event_node() {
+        let sysfs = TempDir::new("vendor-input");
+        sysfs.write("uevent", "DRIVER=asus\nHID_NAME=ASUSTeK Computer Inc. N-KEY Device\nHID_PHYS=usb-0000:00:14.0-3/input0\n");
+        for (input, name, phys, event) in [
+            ("input10", "ASUSTeK Computer Inc. N-KEY Device Keyboard", "usb-0000:00:14.0-3/input0", "event5"),
+            ("input11", "ASUSTeK Computer Inc. N-KEY Device Consumer Control", "usb-0000:00:14.0-3/input0", "event6"),
+            ("input12", "ASUSTeK Computer Inc. N-KEY Device", "usb-0000:00:14.0-3/input0", "event7"),
+            // same name behind another interface
+            ("input13", "ASUSTeK Computer Inc. N-KEY Device", "usb-0000:00:14.0-3/input2", "event8"),
+        ] {
+            sysfs.write(&format!("input/{}/name", input), &format!("{}\n", name));
+            sysfs.write(&format!("input/{}/phys", input), &format!("{}\n", phys));
+            sysfs.create_dir(&format!("input/{}/{}", input, event));
+        }
+        assert_eq!(vendor_event_nodes(sysfs.path()), vec!["/dev/input/event7".to_string()]);
+    }
+
+    #[test]
+    fn finds_nothing_without_a_hid_name() {
+        let sysfs = TempDir::new("vendor-input-no-uevent");
+        sysfs.write("input/input12/name", "\n");
+        sysfs.write("input/input12/phys", "\n");
+        sysfs.create_dir("input/input12/event7");
+        assert!(vendor_event_nodes(sysfs.path()).is_empty());
+    }
+}
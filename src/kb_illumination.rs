@@ -1,50 +1,371 @@
-static KB_BRIGHTNESS_PATH: &str = "/sys/class/leds/asus::kbd_backlight";
-static mut SAVED_VALUE: u32 = 0;
-
-pub fn cycle() {
-    println!("kb brightness cycle!");
-    let max_brightness = get_max_brightness();
-    let current_brightness = get_current_brightness();
-    let new_brightness = if current_brightness < max_brightness {
-        current_brightness + 1
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use crate::apkt_config::{KbBrightnessConfig, LedConfig};
+use crate::privileges;
+use crate::sysfs;
+use crate::state::{load_backlight_state, load_tablet_backlight_state, save_tablet_backlight_state};
+use tracing::{debug, warn};
+
+pub static LEDS_ROOT: &str = "/sys/class/leds";
+static LED_PATHS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// leds named in backlight.led, empty to pick the preferred one
+static CONFIGURED_LEDS: OnceLock<Vec<String>> = OnceLock::new();
+static SAVED_VALUES: Mutex<BTreeMap<String, u32>> = Mutex::new(BTreeMap::new());
+static DISABLED: AtomicBool = AtomicBool::new(false);
+// direction of travel of each led for the "bounce" cycle mode, true while going down
+static BOUNCING_DOWN: Mutex<BTreeMap<String, bool>> = Mutex::new(BTreeMap::new());
+
+// pick the keyboard backlight led(s) to manage.  must be called once before the other functions
+pub fn init(led_config: Option<&LedConfig>, hid_bus_path: &str) -> Vec<String> {
+    CONFIGURED_LEDS.get_or_init(|| led_config.map(LedConfig::names).unwrap_or_default());
+    let paths = select_leds(hid_bus_path);
+    if paths.is_empty() {
+        warn!(target: "backlight", "No keyboard backlight led found in {}", LEDS_ROOT);
+    }
+    use_leds(&paths);
+    paths
+}
+
+// pick the leds again after an input device was plugged in, e.g. a keyboard with its own led.
+// returns the new leds if they changed
+pub fn rediscover(hid_bus_path: &str) -> Option<Vec<String>> {
+    let paths = select_leds(hid_bus_path);
+    if paths == led_paths() {
+        return None;
+    }
+    use_leds(&paths);
+    Some(paths)
+}
+
+// the configured leds, or the preferred one of the discovered leds
+fn select_leds(hid_bus_path: &str) -> Vec<String> {
+    let configured = CONFIGURED_LEDS.get_or_init(Vec::new);
+    if configured.is_empty() {
+        return discover_leds(LEDS_ROOT, hid_bus_path).into_iter().take(1).collect();
+    }
+    configured.iter()
+        .map(|led| if led.contains('/') { led.clone() } else { format!("{}/{}", LEDS_ROOT, led) })
+        .collect()
+}
+
+fn use_leds(paths: &[String]) {
+    // open the attributes now, they can't be opened once privileges are dropped
+    for led_path in paths {
+        for attribute in ["brightness", "max_brightness"] {
+            if let Err(e) = sysfs::open_attribute(&format!("{}/{}", led_path, attribute)) {
+                warn!(target: "backlight", "Unable to open {}/{}: {}", led_path, attribute, e);
+            }
+        }
+    }
+    // still in tablet mode from before a restart, keep the level to restore when leaving it
+    {
+        let mut saved_values = SAVED_VALUES.lock().expect("Failed to lock saved brightness");
+        for led_path in paths {
+            if saved_values.contains_key(led_path) {
+                continue;
+            }
+            if let Some(level) = load_tablet_backlight_state(led_path) {
+                saved_values.insert(led_path.clone(), level);
+            }
+        }
+    }
+    *LED_PATHS.lock().expect("Failed to lock backlight leds") = paths.to_vec();
+}
+
+// find all *kbd_backlight* leds.  the ones belonging to the managed hid device come first
+pub fn discover_leds(leds_root: &str, hid_bus_path: &str) -> Vec<String> {
+    let mut tied: Vec<String> = Vec::new();
+    let mut others: Vec<String> = Vec::new();
+    let entries = match std::fs::read_dir(leds_root) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!(target: "backlight", "Failed to read {}: {}", leds_root, e);
+            return tied;
+        }
+    };
+    let hid_device = std::fs::canonicalize(hid_bus_path).ok();
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.contains("kbd_backlight") {
+            continue;
+        }
+        let path = format!("{}/{}", leds_root, name);
+        // the led directory resolves to somewhere below the device that registered it
+        let is_tied = match (&hid_device, std::fs::canonicalize(&path)) {
+            (Some(hid_device), Ok(led_device)) => led_device.starts_with(hid_device),
+            _ => false,
+        };
+        if is_tied {
+            tied.push(path);
+        } else {
+            others.push(path);
+        }
+    }
+    tied.sort();
+    others.sort();
+    tied.append(&mut others);
+    tied // return value
+}
+
+fn led_paths() -> Vec<String> {
+    LED_PATHS.lock().expect("Failed to lock backlight leds").clone()
+}
+
+// errors if the configured cycle can't be used with the managed leds
+pub fn validate_cycle(config: &KbBrightnessConfig) -> Result<(), String> {
+    if !["wrap", "bounce", "up_only"].contains(&config.mode.as_str()) {
+        return Err(format!("Invalid kb_brightness_cycle.mode value in config: {}", config.mode));
+    }
+    for led_path in led_paths() {
+        let max_brightness = match get_max_brightness(&led_path) {
+            Ok(max_brightness) => max_brightness,
+            Err(e) => {
+                warn!(target: "backlight", "Unable to read max_brightness of {}: {}", led_path, e);
+                continue;
+            }
+        };
+        let sequence = cycle_sequence(config, max_brightness);
+        if sequence.is_empty() {
+            return Err(format!("kb_brightness_cycle has no brightness steps left for {}", led_path));
+        }
+        for level in sequence {
+            if level > max_brightness {
+                return Err(format!(
+                    "kb_brightness_cycle.sequence value {} is above max_brightness {} of {}",
+                    level, max_brightness, led_path
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// returns the new level of each led.  leds that can't be read or written (e.g. unplugged) are skipped
+pub fn cycle(config: &KbBrightnessConfig) -> Vec<(String, u32)> {
+    debug!(target: "backlight", "Cycling keyboard brightness");
+    let mut levels = Vec::new();
+    for led_path in led_paths() {
+        let brightness = get_max_brightness(&led_path)
+            .and_then(|max_brightness| Ok((max_brightness, get_current_brightness(&led_path)?)));
+        let (max_brightness, current_brightness) = match brightness {
+            Ok(brightness) => brightness,
+            Err(e) => {
+                warn!(target: "backlight", "Unable to read brightness of {}: {}", led_path, e);
+                continue;
+            }
+        };
+        let sequence = cycle_sequence(config, max_brightness);
+        if sequence.is_empty() {
+            continue;
+        }
+        let new_brightness = {
+            let mut bouncing_down = BOUNCING_DOWN.lock().expect("Failed to lock bounce direction");
+            let down = bouncing_down.entry(led_path.clone()).or_default();
+            next_step(&sequence, &config.mode, current_brightness, down)
+        };
+        if set_brightness(&led_path, new_brightness) {
+            levels.push((led_path, new_brightness));
+        }
+    }
+    levels
+}
+
+// the configured sequence, or 0..=max if none is set
+fn cycle_sequence(config: &KbBrightnessConfig, max_brightness: u32) -> Vec<u32> {
+    let sequence: Vec<u32> = match &config.sequence {
+        Some(sequence) => sequence.clone(),
+        None => (0..=max_brightness).collect(),
+    };
+    if config.skip_zero {
+        sequence.into_iter().filter(|level| *level != 0).collect()
     } else {
-        0
+        sequence
+    }
+}
+
+fn next_step(sequence: &[u32], mode: &str, current: u32, bouncing_down: &mut bool) -> u32 {
+    let position = match sequence.iter().position(|level| *level == current) {
+        Some(position) => position,
+        None if mode == "up_only" => {
+            // not on a step, go to the next one up or start over
+            return *sequence.iter()
+                .filter(|level| **level > current)
+                .min()
+                .unwrap_or(&sequence[0]);
+        }
+        None => {
+            // not on a step (changed outside of this tool), go to the closest one
+            return *sequence.iter()
+                .min_by_key(|level| level.abs_diff(current))
+                .expect("Empty brightness sequence");
+        }
     };
-    set_brightness(new_brightness);
+    let last = sequence.len() - 1;
+    match mode {
+        "bounce" => {
+            if position == last {
+                *bouncing_down = true;
+            } else if position == 0 {
+                *bouncing_down = false;
+            }
+            if last == 0 {
+                sequence[0]
+            } else if *bouncing_down {
+                sequence[position - 1]
+            } else {
+                sequence[position + 1]
+            }
+        }
+        // "up_only" and "wrap" both start over after the last step
+        _ => sequence[(position + 1) % sequence.len()],
+    }
 }
 
 pub fn disable_toggle(disable: bool) {
-    unsafe {
+    let mut saved_values = SAVED_VALUES.lock().expect("Failed to lock saved brightness");
+    DISABLED.store(disable, Ordering::Relaxed);
+    for led_path in led_paths() {
         if disable {
-            // Save current brightness
-            SAVED_VALUE = get_current_brightness();
+            let current_brightness = match get_current_brightness(&led_path) {
+                Ok(current_brightness) => current_brightness,
+                Err(e) => {
+                    warn!(target: "backlight", "Unable to read brightness of {}: {}", led_path, e);
+                    continue;
+                }
+            };
+            // Save current brightness, unless it's already saved from before a restart
+            if saved_values.contains_key(&led_path) && current_brightness == 0 {
+                continue;
+            }
+            saved_values.insert(led_path.clone(), current_brightness);
             // Turn off keyboard backlight
-            set_brightness(0);
+            set_brightness(&led_path, 0);
         } else {
             // Restore saved brightness
-            set_brightness(SAVED_VALUE);
+            set_brightness(&led_path, saved_values.remove(&led_path).unwrap_or(0));
+        }
+    }
+    let levels: Vec<(String, u32)> = saved_values.iter()
+        .map(|(led_path, level)| (led_path.clone(), *level))
+        .collect();
+    save_tablet_backlight_state(&levels);
+}
+
+// false if any managed led can't be written, e.g. when running without root or the udev rules
+pub fn writable() -> bool {
+    led_paths().iter().all(|led_path| privileges::can_access(&format!("{}/brightness", led_path), true))
+}
+
+// true while the backlight is turned off for tablet mode
+pub fn is_disabled() -> bool {
+    DISABLED.load(Ordering::Relaxed)
+}
+
+// set every led to the given level, clamped to each led's max_brightness
+pub fn set_all_levels(value: u32) {
+    for led_path in led_paths() {
+        match get_max_brightness(&led_path) {
+            Ok(max_brightness) => {
+                set_brightness(&led_path, value.min(max_brightness));
+            }
+            Err(e) => warn!(target: "backlight", "Unable to read max_brightness of {}: {}", led_path, e),
         }
     }
 }
 
-fn get_max_brightness() -> u32 {
-    let max_brightness_path = format!("{}/max_brightness", KB_BRIGHTNESS_PATH);
-    let contents = std::fs::read_to_string(max_brightness_path)
-        .expect("Unable to read max_brightness file");
-    contents.trim().parse::<u32>()
-        .expect("Unable to parse max_brightness value")
+// levels saved with the cycle key, for the leds that have one
+pub fn saved_levels() -> Vec<(String, u32)> {
+    led_paths().into_iter()
+        .filter_map(|led_path| load_backlight_state(&led_path).map(|level| (led_path, level)))
+        .collect()
+}
+
+pub fn current_levels() -> Vec<(String, u32)> {
+    led_paths().into_iter()
+        .filter_map(|led_path| get_current_brightness(&led_path).ok().map(|level| (led_path, level)))
+        .collect()
+}
+
+pub fn restore_levels(levels: &[(String, u32)]) {
+    for (led_path, level) in levels {
+        set_brightness(led_path, *level);
+    }
+}
+
+pub fn set_level(led_path: &str, value: u32) {
+    set_brightness(led_path, value);
+}
+
+fn read_level(path: &str) -> std::io::Result<u32> {
+    sysfs::read_attribute(path)?
+        .parse::<u32>()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+fn get_max_brightness(led_path: &str) -> std::io::Result<u32> {
+    read_level(&format!("{}/max_brightness", led_path))
+}
+
+fn get_current_brightness(led_path: &str) -> std::io::Result<u32> {
+    read_level(&format!("{}/brightness", led_path))
 }
 
-fn get_current_brightness() -> u32 {
-    let brightness_path = format!("{}/brightness", KB_BRIGHTNESS_PATH);
-    let contents = std::fs::read_to_string(brightness_path)
-        .expect("Unable to read brightness file");
-    contents.trim().parse::<u32>()
-        .expect("Unable to parse brightness value")
+// false if the led couldn't be written, e.g. because its keyboard was unplugged
+fn set_brightness(led_path: &str, value: u32) -> bool {
+    let brightness_path = format!("{}/brightness", led_path);
+    match sysfs::write_attribute(&brightness_path, &value.to_string()) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(target: "backlight", "Unable to write {}: {}", brightness_path, e);
+            false
+        }
+    }
 }
 
-fn set_brightness(value: u32) {
-    let brightness_path = format!("{}/brightness", KB_BRIGHTNESS_PATH);
-    std::fs::write(brightness_path, value.to_string())
-        .expect("Unable to write brightness file");
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wraps_after_the_last_step() {
+        let mut down = false;
+        assert_eq!(next_step(&[1, 3, 0], "wrap", 1, &mut down), 3);
+        assert_eq!(next_step(&[1, 3, 0], "wrap", 0, &mut down), 1);
+        assert_eq!(next_step(&[0, 1, 2, 3], "up_only", 3, &mut down), 0);
+        assert_eq!(next_step(&[1, 2, 3], "up_only", 3, &mut down), 1);
+    }
+
+    #[test]
+    fn goes_to_the_closest_step_off_the_sequence() {
+        let mut down = false;
+        assert_eq!(next_step(&[0, 2, 3], "wrap", 1, &mut down), 0);
+        assert_eq!(next_step(&[0, 2, 3], "bounce", 4, &mut down), 3);
+        // up_only never goes down from there
+        assert_eq!(next_step(&[0, 2, 3], "up_only", 1, &mut down), 2);
+        assert_eq!(next_step(&[0, 2, 3], "up_only", 4, &mut down), 0);
+    }
+
+    #[test]
+    fn bounces_at_both_ends() {
+        let mut down = false;
+        let mut level = 0;
+        let mut levels = Vec::new();
+        for _ in 0..6 {
+            level = next_step(&[0, 1, 2, 3], "bounce", level, &mut down);
+            levels.push(level);
+        }
+        assert_eq!(levels, vec![1, 2, 3, 2, 1, 0]);
+        assert_eq!(next_step(&[2], "bounce", 2, &mut down), 2);
+    }
+
+    #[test]
+    fn bounce_direction_is_per_led() {
+        let (mut first, mut second) = (false, false);
+        assert_eq!(next_step(&[0, 1, 2], "bounce", 2, &mut first), 1);
+        // the other led still goes up from the middle
+        assert_eq!(next_step(&[0, 1, 2], "bounce", 1, &mut second), 2);
+        assert_eq!(next_step(&[0, 1, 2], "bounce", 1, &mut first), 0);
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::OnceLock;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+use crate::apkt_config::LoggingConfig;
+
+// targets that can get their own level in [logging.modules]
+pub static MODULES: [&str; 4] = ["hid", "bpf", "backlight", "events"];
+// journald field prefix, e.g. ASUS_SCANCODE
+static FIELD_PREFIX: &str = "ASUS";
+
+static FILTER_HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+// filter directives from the config, e.g. "info,bpf=warn".  RUST_LOG wins if set
+fn build_filter(config: &LoggingConfig) -> Result<EnvFilter, String> {
+    if let Ok(directives) = std::env::var("RUST_LOG") {
+        return EnvFilter::try_new(&directives).map_err(|e| format!("Invalid RUST_LOG: {}", e));
+    }
+    let mut directives = vec![config.level.clone()];
+    for (module, level) in &config.modules {
+        if !MODULES.contains(&module.as_str()) {
+            return Err(format!("Unknown module in logging.modules: {} (expected one of {:?})", module, MODULES));
+        }
+        directives.push(format!("{}={}", module, level));
+    }
+    EnvFilter::try_new(directives.join(","))
+        .map_err(|e| format!("Invalid logging level in config: {}", e))
+}
+
+// set up the global subscriber.  logs go to journald natively when enabled, stdout otherwise
+pub fn init(config: &LoggingConfig) {
+    let filter = build_filter(config).unwrap_or_else(|e| panic!("{}", e));
+    let (filter, handle) = reload::Layer::new(filter);
+    FILTER_HANDLE.set(handle).expect("Logging already initialized");
+
+    // JOURNAL_STREAM is set by systemd when stdout goes to the journal
+    let use_journald = match config.journald.as_str() {
+        "on" => true,
+        "off" => false,
+        "auto" => std::env::var_os("JOURNAL_STREAM").is_some(),
+        other => panic!("Invalid logging.journald value in config: {}", other),
+    };
+    let journald = if use_journald {
+        match tracing_journald::layer() {
+            Ok(layer) => Some(layer.with_field_prefix(Some(FIELD_PREFIX.to_string()))),
+            Err(e) => {
+                eprintln!("Unable to connect to journald, logging to stdout: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let stdout = if journald.is_none() { Some(fmt::layer()) } else { None };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(journald)
+        .with(stdout)
+        .init();
+}
+
+// apply changed levels on config reload
+pub fn reload(config: &LoggingConfig) -> Result<(), String> {
+    let filter = build_filter(config)?;
+    let handle = FILTER_HANDLE.get().ok_or("Logging not initialized")?;
+    handle.reload(filter).map_err(|e| format!("Unable to change log levels: {}", e))
+}
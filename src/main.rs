@@ -1,50 +1,123 @@
+mod ambient_light;
 mod apkt_config;
+mod backlight_idle;
 mod bpf_loader;
+mod cli;
+mod doctor;
+mod fn_lock;
 mod hid;
+mod hidraw_remap;
 mod kb_illumination;
+mod logging;
+mod privileges;
+mod recording;
+mod remap_model;
+mod sd_notify;
 mod state;
+mod status;
+mod sysfs;
+#[cfg(test)]
+mod test_util;
+mod udev_rules;
+mod uhid;
 
 use std::collections::HashSet;
 use std::sync::{Arc};
 use std::time::Duration;
 use tokio::sync::{Mutex, RwLock};
-use evdev::{Device, EventType, KeyCode, SwitchCode};
-use crate::apkt_config::{get_config, ConfigWrapper};
-use crate::bpf_loader::start_bpf;
-use crate::hid::{get_hardware_info, get_possible_event_paths, toggle_fn_lock, HidDeviceInfo};
-use crate::state::{load_state, save_state};
+use evdev::{EventType, KeyCode, SwitchCode};
+use crate::apkt_config::{get_config, try_get_config, ConfigWrapper};
+use crate::bpf_loader::{set_remaps, start_bpf, stop_bpf};
+use crate::cli::{parse_args, print_usage, resolve_directory, DEFAULT_CONFIG_PATH};
+use crate::fn_lock::{select_backend, EmulatedFnLock, FnLockBackend};
+use crate::hid::{find_vendor_interface, get_hardware_info, get_possible_event_paths, keyboard_event_nodes, switch_event_nodes, switch_state, INPUT_ROOT};
+use crate::sd_notify::Heartbeat;
+use crate::state::{flush_state, load_state, save_backlight_state, save_state, set_state_dir};
+use crate::status::{print_status, set_runtime_dir, set_status};
 use notify::{Config, Error, Event, PollWatcher, RecursiveMode, Watcher};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+// device tasks, aborted on shutdown so grabbed devices are released
+static DEVICE_TASKS: std::sync::Mutex<Vec<JoinHandle<()>>> = std::sync::Mutex::new(Vec::new());
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // read args to get config path
-     let args: Vec<String> = std::env::args().collect();
-    let mut config_path = "asus-px-keyboard-tool.conf";
-    if args.len() == 2 {
-        // allow user to specify config path as first arg
-        config_path = &args[1];
+    // read args to get config path and directories
+    let args: Vec<String> = std::env::args().collect();
+    let cli_args = match parse_args(&args) {
+        Ok(cli_args) => cli_args,
+        Err(e) => {
+            println!("{}", e);
+            print_usage(&args[0]);
+            return Err(e.into());
+        }
+    };
+
+    if cli_args.command.as_deref() == Some(privileges::OPEN_HELPER_COMMAND) {
+        return privileges::run_open_helper();
+    }
+    if cli_args.command.as_deref() == Some("install-udev-rules") {
+        return udev_rules::install_udev_rules(&cli_args.command_args);
+    }
+    if cli_args.command.as_deref() == Some("doctor") {
+        return doctor::run_doctor();
+    }
+    if cli_args.command.as_deref() == Some("simulate") {
+        return uhid::run_simulate(&cli_args.command_args);
+    }
+    if cli_args.command.as_deref() == Some("stats") {
+        return bpf_loader::print_stats();
+    }
+    if cli_args.command.as_deref() == Some("simulate-remap") {
+        return remap_model::simulate_remap(&cli_args.command_args, DEFAULT_CONFIG_PATH);
+    }
+    if cli_args.command.as_deref() == Some("describe-device") {
+        return hid::describe_device(&cli_args.command_args);
+    }
+    if cli_args.command.as_deref() == Some("record") {
+        return recording::run_record(&cli_args.command_args);
     }
-    println!("Using config path: {}", config_path);
-    if args.len() >= 3 {
-        println!("Too many arguments");
-        println!("Usage: {} [config_path]", args[0]);
-        return Err("Too many arguments".into());
+    if cli_args.command.as_deref() == Some("replay") {
+        return recording::run_replay(&cli_args.command_args);
     }
 
-    let config = &Arc::new(get_config(config_path));
+    if cli_args.command.as_deref() == Some("status") {
+        // the config is only needed for [paths], a missing default one is fine
+        let config_path = cli_args.command_args.first().map(|path| path.as_str()).unwrap_or(DEFAULT_CONFIG_PATH);
+        let config_runtime_dir = match try_get_config(config_path) {
+            Ok(config) => config.paths.runtime_dir,
+            Err(_) if cli_args.command_args.is_empty() => None,
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(dir) = resolve_directory(&cli_args.runtime_dir, &config_runtime_dir, "RUNTIME_DIRECTORY") {
+            set_runtime_dir(&dir);
+        }
+        return print_status();
+    }
 
-    // convert string to enum
-    let mut target_keycodes: Vec<KeyCode> = Vec::new();
-    if config.fnlock.enabled && config.fnlock.keycode_enum.is_some(){
-        target_keycodes.push(config.fnlock.keycode_enum.unwrap());
+    // allow user to specify config path as first arg
+    let config_path = cli_args.config_path.as_deref().unwrap_or(DEFAULT_CONFIG_PATH);
+
+    let mut config = get_config(config_path);
+    logging::init(&config.logging);
+    info!("Using config path: {}", config_path);
+
+    if let Some(dir) = resolve_directory(&cli_args.state_dir, &config.paths.state_dir, "STATE_DIRECTORY") {
+        set_state_dir(&dir);
     }
-    if config.kb_brightness_cycle.enabled && config.kb_brightness_cycle.keycode_enum.is_some(){
-        target_keycodes.push(config.kb_brightness_cycle.keycode_enum.unwrap());
+    if let Some(dir) = resolve_directory(&cli_args.runtime_dir, &config.paths.runtime_dir, "RUNTIME_DIRECTORY") {
+        set_runtime_dir(&dir);
     }
+    info!(state_dir = state::state_dir(), runtime_dir = status::runtime_dir(), "Using directories");
+
+    let mut target_keycodes = get_target_keycodes(&config);
 
     let mut dev_info = get_hardware_info(&target_keycodes);
     if config.compatibility.hid_id_override.is_some() {
-        println!(
+        info!(
+            target: "hid",
             "Overriding HID ID from {} to {}",
             dev_info.hid_id,
             config.compatibility.hid_id_override.unwrap()
@@ -52,7 +125,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         dev_info.hid_id = config.compatibility.hid_id_override.unwrap();
     }
     if config.compatibility.hid_path_override.is_some() {
-        println!(
+        info!(
+            target: "hid",
             "Overriding HID path from {} to {}",
             dev_info.hidraw_device_path,
             config.compatibility.hid_path_override.as_ref().unwrap()
@@ -60,24 +134,130 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         dev_info.hidraw_device_path = config.compatibility.hid_path_override.as_ref().unwrap().to_string();
     }
     if config.compatibility.event_path_override.is_some() {
-        println!(
+        info!(
+            target: "hid",
             "Overriding event path to {}",
             config.compatibility.event_path_override.as_ref().unwrap()
         );
         dev_info.possible_event_paths = vec![config.compatibility.event_path_override.as_ref().unwrap().to_string()];
 
     }
-    println!("HID ID: {}", dev_info.hid_id);
-    println!("Possible event devices: {:?}", dev_info.possible_event_paths);
-    println!("HIDRAW device: {}", dev_info.hidraw_device_path);
-    if config.bpf.enabled {
-        println!("BPF enabled");
-        start_bpf(dev_info.hid_id as i32, config.bpf.remaps.as_ref());
-    } else {
-        println!("BPF disabled in config");
+    info!(
+        target: "hid",
+        hid_id = dev_info.hid_id,
+        hidraw_device = %dev_info.hidraw_device_path,
+        "Possible event devices: {:?}", dev_info.possible_event_paths
+    );
+    set_status("hid_id", &dev_info.hid_id.to_string());
+    set_status("hidraw_device", &dev_info.hidraw_device_path);
+
+    let leds = kb_illumination::init(config.backlight.led.as_ref(), &dev_info.bus_path);
+    info!(target: "backlight", "Keyboard backlight leds: {:?}", leds);
+    set_status("kbd_backlight_leds", &leds.join(", "));
+
+    // without root (e.g. a user service with the rules from install-udev-rules) some devices may not
+    // be accessible.  turn those features off instead of failing on the first write
+    let mut unavailable: Vec<&str> = Vec::new();
+    let backlight_writable = kb_illumination::writable();
+    if !backlight_writable {
+        warn!(target: "backlight", "Keyboard backlight is not writable, disabling backlight features");
+        unavailable.push("backlight");
+    }
+    let mut bpf_running = false;
+    // remaps use hid-bpf where the kernel supports it, otherwise hidraw and uinput
+    let mut remap_backend = match config.bpf.backend.as_str() {
+        "bpf" | "hidraw" => config.bpf.backend.clone(),
+        "auto" if doctor::hid_bpf_supported() && privileges::bpf_allowed() => "bpf".to_string(),
+        "auto" => "hidraw".to_string(),
+        other => panic!("Invalid bpf.backend value in config: {}", other),
+    };
+    if config.bpf.enabled && remap_backend == "bpf" && !privileges::bpf_allowed() {
+        warn!(target: "bpf", "BPF needs CAP_BPF and CAP_PERFMON, disabling it");
+        unavailable.push("bpf");
+    }
+    if config.bpf.enabled && remap_backend == "hidraw" && !hidraw_remap::is_usable(&dev_info.hidraw_device_path) {
+        warn!(target: "bpf", "Remapping through hidraw needs read access to the hidraw device and uinput, disabling it");
+        unavailable.push("bpf");
+    }
+    disable_features(&mut config, &unavailable);
+    if dev_info.possible_event_paths.is_empty() && !target_keycodes.is_empty() {
+        warn!(target: "events", "No usable event devices found.  Without root, install the udev rules with `install-udev-rules`");
+    }
+
+    if config.kb_brightness_cycle.enabled {
+        kb_illumination::validate_cycle(&config.kb_brightness_cycle).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    // the switch only reports changes, so starting in tablet mode needs its current state
+    if backlight_writable && config.tablet_kb_backlight_disable.enabled
+        && switch_state(INPUT_ROOT, SwitchCode::SW_TABLET_MODE) == Some(true)
+    {
+        info!(target: "events", "Tablet mode enabled at startup, disabling keyboard backlight");
+        kb_illumination::disable_toggle(true);
+    }
+    // apply initial backlight level
+    if backlight_writable && !kb_illumination::is_disabled() {
+        if config.backlight.boot_default == "last" {
+            let levels = kb_illumination::saved_levels();
+            if !levels.is_empty() {
+                info!(target: "backlight", "Restoring keyboard backlight levels {:?}", levels);
+                kb_illumination::restore_levels(&levels);
+            }
+        } else {
+            let level = config.backlight.boot_default.parse::<u32>().unwrap_or_else(|_| panic!(
+                "Invalid backlight.boot_default value in config: {}",
+                config.backlight.boot_default
+            ));
+            info!(target: "backlight", level, "Setting keyboard backlight level");
+            kb_illumination::set_all_levels(level);
+        }
+    }
+    if backlight_idle::idle_enabled(&config.backlight) {
+        backlight_idle::start_idle_tracking(config.backlight.clone());
+    }
+    if config.backlight.ambient.enabled {
+        ambient_light::start_ambient_backlight(config.backlight.ambient.clone());
+    }
+    if !config.bpf.enabled {
+        info!(target: "bpf", "BPF disabled in config");
+    }
+    if config.bpf.enabled && remap_backend == "bpf" {
+        info!(target: "bpf", "BPF enabled");
+        match start_bpf(dev_info.hid_id as i32, &config.bpf) {
+            Ok(()) => {
+                set_status("remap_backend", "bpf");
+                bpf_running = true;
+            }
+            Err(e) if config.bpf.backend == "auto" && hidraw_remap::is_usable(&dev_info.hidraw_device_path) => {
+                warn!(target: "bpf", "{}, remapping through hidraw instead", e);
+                remap_backend = "hidraw".to_string();
+            }
+            Err(e) => {
+                error!(target: "bpf", "{}, disabling BPF", e);
+                unavailable.push("bpf");
+                disable_features(&mut config, &unavailable);
+            }
+        }
+    }
+    if config.bpf.enabled && remap_backend == "hidraw" {
+        info!(target: "bpf", "HID-BPF unavailable, remapping through hidraw");
+        match hidraw_remap::start_hidraw_remap(&dev_info.hidraw_device_path, &dev_info.bus_path, &config.bpf) {
+            Some(device_node) => {
+                // the remapped keys come from the uinput device now
+                if !target_keycodes.is_empty() && !dev_info.possible_event_paths.contains(&device_node) {
+                    dev_info.possible_event_paths.push(device_node);
+                }
+                set_status("remap_backend", "hidraw");
+            }
+            None => {
+                unavailable.push("bpf");
+                disable_features(&mut config, &unavailable);
+            }
+        }
     }
 
     let mut state = false;
+    let mut fn_lock_backend = FnLockBackend::Hid { hidraw_path: dev_info.hidraw_device_path.clone() };
 
     if config.fnlock.enabled {
         // apply initial fnlock state
@@ -93,28 +273,74 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 config.fnlock.boot_default
             );
         }
-        toggle_fn_lock(&dev_info.hidraw_device_path, state);
-        save_state(state);
+        fn_lock_backend = select_backend(&config.fnlock, &dev_info.hidraw_device_path);
+        if fn_lock_backend.is_usable() {
+            info!(target: "hid", backend = fn_lock_backend.name(), "Fn-Lock backend selected");
+            set_status("fnlock_backend", fn_lock_backend.name());
+            fn_lock_backend.apply(state);
+            save_state(state);
+            for path in emulated_event_paths(&fn_lock_backend, &dev_info.possible_event_paths) {
+                dev_info.possible_event_paths.push(path);
+            }
+            set_status("fnlock_state", if state { "on" } else { "off" });
+        } else {
+            warn!(target: "hid", backend = fn_lock_backend.name(), "Fn-Lock backend is not accessible, disabling fn-lock");
+            unavailable.push("fnlock");
+            disable_features(&mut config, &unavailable);
+        }
+    }
+    let target_switches = get_target_switches(&config);
+    for path in switch_event_nodes(INPUT_ROOT, &target_switches) {
+        if !dev_info.possible_event_paths.contains(&path) {
+            dev_info.possible_event_paths.push(path);
+        }
     }
+    if !unavailable.is_empty() {
+        set_status("unavailable_features", &unavailable.join(", "));
+    }
+    let config = &Arc::new(RwLock::new(config));
 
     let active_paths: HashSet<String> = HashSet::new();
     let active_paths_mutex = &Arc::new(RwLock::new(active_paths));
-    let dev_info_arc = &Arc::new(dev_info.clone());
+    let backend_arc = &Arc::new(fn_lock_backend);
     let state_mutex = &Arc::new(Mutex::new(state));
-    start_sleep_tracking(Arc::clone(state_mutex), dev_info.hidraw_device_path.clone());
+    start_sleep_tracking(Arc::clone(state_mutex), Arc::clone(backend_arc));
 
     {
         let mut data = active_paths_mutex.write().await;
         for path in &dev_info.possible_event_paths {
-            data.insert(path.clone());
+            if start_device_thread(path.clone(), Arc::clone(config), Arc::clone(state_mutex),
+                                   Arc::clone(backend_arc), Arc::clone(active_paths_mutex)) {
+                data.insert(path.clone());
+            }
         }
     }
 
-    for path in &dev_info.possible_event_paths {
-        start_device_thread(path.clone(), Arc::clone(config), Arc::clone(state_mutex),
-                            Arc::clone(dev_info_arc), Arc::clone(active_paths_mutex));
+    // everything that needs root is open now
+    let privileges_config = config.read().await.privileges.clone();
+    if privileges_config.enabled {
+        let status_files = [status::STATUS_FILE];
+        let mut owned_files: Vec<(&str, &[&str])> = vec![
+            (state::state_dir(), &state::STATE_FILES),
+            (status::runtime_dir(), &status_files),
+        ];
+        // so the pinned stats maps can be removed on shutdown
+        if bpf_running {
+            owned_files.push((bpf_loader::PIN_DIR, &[]));
+        }
+        privileges::drop_privileges(&privileges_config, &owned_files);
     }
 
+    // discovery, bpf and the initial fn-lock state are done
+    notify_status(active_paths_mutex.read().await.len(), &*config.read().await, backend_arc);
+    sd_notify::heartbeat(Heartbeat::EventLoop);
+    sd_notify::notify("READY=1");
+    sd_notify::start_watchdog(config.read().await.bpf.enabled);
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sighup = signal(SignalKind::hangup())?;
+
     // watch for new event devices every 3 seconds
     let poll_config = Config::default()
         .with_compare_contents(true) // crucial part for pseudo filesystems
@@ -123,15 +349,28 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut watcher = PollWatcher::new(move |evt| {tx.blocking_send(evt).unwrap()}, poll_config).unwrap();
     watcher.watch("/dev/input".as_ref(), RecursiveMode::NonRecursive)?;
+    let mut heartbeat_interval = tokio::time::interval(Duration::from_secs(5));
     loop {
-        let _res = rx.recv().await;
+        let _res = tokio::select! {
+            res = rx.recv() => res,
+            _ = heartbeat_interval.tick() => {
+                sd_notify::heartbeat(Heartbeat::EventLoop);
+                continue;
+            }
+            _ = sigterm.recv() => break,
+            _ = sigint.recv() => break,
+            _ = sighup.recv() => {
+                reload_config(config_path, config, &unavailable, &mut target_keycodes).await;
+                continue;
+            }
+        };
         if _res.is_none() {
-            println!("Watcher channel closed, exiting");
+            error!(target: "events", "Watcher channel closed, exiting");
             break;
         }
         let evt = _res.unwrap();
         if evt.is_err() {
-            println!("Watcher error: {:?}", evt);
+            warn!(target: "events", "Watcher error: {:?}", evt);
             continue;
         }
         let evt = evt.unwrap();
@@ -148,15 +387,38 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 continue;
             }
 
+            // a keyboard plugged in again comes with a new backlight led
+            let bus_path = find_vendor_interface().unwrap_or_default();
+            if let Some(leds) = kb_illumination::rediscover(&bus_path) {
+                info!(target: "backlight", "Keyboard backlight leds changed: {:?}", leds);
+                set_status("kbd_backlight_leds", &leds.join(", "));
+                if kb_illumination::is_disabled() {
+                    kb_illumination::disable_toggle(true);
+                } else if !backlight_idle::is_faded() {
+                    kb_illumination::restore_levels(&kb_illumination::saved_levels());
+                }
+            }
+
+            if config.read().await.bpf.enabled {
+                hidraw_remap::reattach(&bus_path);
+            }
+            backlight_idle::rediscover_touchpads();
+
             let mut to_add: Vec<String> = vec![];
             {
                 let data = active_paths_mutex.read().await;
 
                 // check for new paths
-                let possible_event_paths = get_possible_event_paths(&target_keycodes);
+                let mut possible_event_paths = get_possible_event_paths(&target_keycodes);
+                possible_event_paths.extend(emulated_event_paths(backend_arc, &possible_event_paths));
+                for path in switch_event_nodes(INPUT_ROOT, &target_switches) {
+                    if !possible_event_paths.contains(&path) {
+                        possible_event_paths.push(path);
+                    }
+                }
                 for path in possible_event_paths {
                     if !data.contains(&path) {
-                        println!("New event device path detected: {}", path);
+                        info!(target: "events", device = %path, "New event device detected");
                         to_add.push(path);
                     }
                 }
@@ -164,18 +426,187 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             if !to_add.is_empty() {
                 let mut data = active_paths_mutex.write().await;
+                let config_guard = config.read().await;
                 for path in to_add {
-                    data.insert(path.clone());
-                    start_device_thread(path.clone(), Arc::clone(config), Arc::clone(state_mutex),
-                                        Arc::clone(dev_info_arc), Arc::clone(active_paths_mutex));
+                    if start_device_thread(path.clone(), Arc::clone(config), Arc::clone(state_mutex),
+                                           Arc::clone(backend_arc), Arc::clone(active_paths_mutex)) {
+                        data.insert(path.clone());
+                    }
                 }
+                notify_status(data.len(), &config_guard, backend_arc);
             }
         }
     }
-    Err("Watcher loop exited unexpectedly".into())
+
+    info!("Shutting down");
+    sd_notify::notify("STOPPING=1");
+    drop(watcher);
+    let state = *state_mutex.lock().await;
+    shutdown(&*config.read().await, state, backend_arc).await;
+    Ok(())
 }
 
-fn start_sleep_tracking(state_mutex: Arc<Mutex<bool>>, hid_path: String) {
+// with emulated fn-lock, the keyboard's devices with function or media keys are needed too, not just
+// the ones with the target keycodes
+fn emulated_event_paths(fn_lock_backend: &FnLockBackend, known_paths: &[String]) -> Vec<String> {
+    if !matches!(fn_lock_backend, FnLockBackend::Emulated) {
+        return Vec::new();
+    }
+    keyboard_event_nodes().into_iter().filter(|path| !known_paths.contains(path)).collect()
+}
+
+// keycodes that make an event device worth listening to
+fn get_target_keycodes(config: &ConfigWrapper) -> Vec<KeyCode> {
+    // convert string to enum
+    let mut target_keycodes: Vec<KeyCode> = Vec::new();
+    if config.fnlock.enabled && config.fnlock.keycode_enum.is_some(){
+        target_keycodes.push(config.fnlock.keycode_enum.unwrap());
+    }
+    if config.kb_brightness_cycle.enabled && config.kb_brightness_cycle.keycode_enum.is_some(){
+        target_keycodes.push(config.kb_brightness_cycle.keycode_enum.unwrap());
+    }
+    target_keycodes
+}
+
+// switches that make an event device worth listening to, their devices usually have no keys.  picked
+// once at startup
+fn get_target_switches(config: &ConfigWrapper) -> Vec<SwitchCode> {
+    let mut target_switches: Vec<SwitchCode> = Vec::new();
+    if config.backlight.ambient.enabled {
+        // opening the lid ends a manual override
+        target_switches.push(SwitchCode::SW_LID);
+    }
+    if config.tablet_kb_backlight_disable.enabled {
+        target_switches.push(SwitchCode::SW_TABLET_MODE);
+    }
+    target_switches
+}
+
+// turn off features whose devices aren't accessible, see the checks in main
+fn disable_features(config: &mut ConfigWrapper, unavailable: &[&str]) {
+    if unavailable.contains(&"backlight") {
+        config.kb_brightness_cycle.enabled = false;
+        config.tablet_kb_backlight_disable.enabled = false;
+        config.backlight.idle_timeout_secs = None;
+        config.backlight.idle_timeout_ac_secs = None;
+        config.backlight.idle_timeout_battery_secs = None;
+        config.backlight.ambient.enabled = false;
+    }
+    if unavailable.contains(&"bpf") {
+        config.bpf.enabled = false;
+    }
+    if unavailable.contains(&"fnlock") {
+        config.fnlock.enabled = false;
+    }
+}
+
+// SIGHUP: re-read the config.  key handling and bpf remaps pick up the changes, everything that is
+// set up once at startup keeps running with the old values until a restart
+async fn reload_config(config_path: &str, config: &RwLock<ConfigWrapper>, unavailable: &[&str],
+                       target_keycodes: &mut Vec<KeyCode>) {
+    info!("Reloading config from {}", config_path);
+    sd_notify::notify("RELOADING=1");
+    let mut new_config = match try_get_config(config_path) {
+        Ok(new_config) => new_config,
+        Err(e) => {
+            error!("Keeping the current config: {}", e);
+            sd_notify::notify("READY=1");
+            return;
+        }
+    };
+    disable_features(&mut new_config, unavailable);
+    if new_config.kb_brightness_cycle.enabled {
+        if let Err(e) = kb_illumination::validate_cycle(&new_config.kb_brightness_cycle) {
+            error!("Keeping the current config: {}", e);
+            sd_notify::notify("READY=1");
+            return;
+        }
+    }
+
+    let mut config = config.write().await;
+    // these are only read at startup
+    if new_config.bpf.enabled != config.bpf.enabled
+        || new_config.bpf.backend != config.bpf.backend
+        || new_config.bpf.map_type != config.bpf.map_type
+        || new_config.bpf.map_size != config.bpf.map_size
+        || new_config.fnlock.backend != config.fnlock.backend
+        || new_config.privileges.enabled != config.privileges.enabled
+        || new_config.backlight.led != config.backlight.led {
+        warn!("Changes to bpf.enabled, bpf.backend, bpf.map_type, bpf.map_size, fnlock.backend, privileges or backlight.led need a restart");
+    }
+    // bpf can't be started after privileges are dropped, only the remaps are updated
+    new_config.bpf.enabled = config.bpf.enabled;
+    if config.bpf.enabled {
+        // only one of them is running
+        set_remaps(&new_config.bpf);
+        hidraw_remap::set_remaps(&new_config.bpf);
+    }
+    // fn-lock stays on the backend picked at startup
+    if !config.fnlock.enabled {
+        new_config.fnlock.enabled = false;
+    }
+
+    if let Err(e) = logging::reload(&new_config.logging) {
+        error!("Keeping the current log levels: {}", e);
+    }
+
+    *target_keycodes = get_target_keycodes(&new_config);
+    *config = new_config;
+    info!("Config reloaded");
+    sd_notify::notify("READY=1");
+}
+
+// SIGTERM/SIGINT: release the devices, save state, apply the exit state, then detach bpf
+async fn shutdown(config: &ConfigWrapper, state: bool, fn_lock_backend: &FnLockBackend) {
+    // stopping the device tasks drops their devices, releasing grabs and uinput devices
+    let tasks: Vec<JoinHandle<()>> = DEVICE_TASKS.lock().expect("Failed to lock device tasks").drain(..).collect();
+    for task in &tasks {
+        task.abort();
+    }
+    for task in tasks {
+        let _ = task.await;
+    }
+
+    if config.fnlock.enabled {
+        save_state(state);
+    }
+    // state is written on its own thread, make sure it's on disk before exiting
+    let _ = tokio::task::spawn_blocking(flush_state).await;
+    // don't leave the keyboard dark if it was faded out for inactivity
+    if backlight_idle::is_faded() {
+        backlight_idle::notify_activity();
+    }
+
+    if config.exit_state.enabled {
+        match config.exit_state.fnlock.as_str() {
+            "keep" => {}
+            "on" | "off" if config.fnlock.enabled => {
+                info!(target: "hid", fn_lock = config.exit_state.fnlock, "Setting exit fn-lock state");
+                fn_lock_backend.apply(config.exit_state.fnlock == "on");
+            }
+            "on" | "off" => {}
+            other => error!("Invalid exit_state.fnlock value in config: {}", other),
+        }
+        if config.exit_state.backlight != "keep" && kb_illumination::writable() {
+            match config.exit_state.backlight.parse::<u32>() {
+                Ok(level) => {
+                    info!(target: "backlight", level, "Setting exit keyboard backlight level");
+                    kb_illumination::set_all_levels(level);
+                }
+                Err(_) => error!("Invalid exit_state.backlight value in config: {}", config.exit_state.backlight),
+            }
+        }
+    }
+
+    if config.bpf.enabled {
+        stop_bpf();
+        hidraw_remap::stop_hidraw_remap();
+    }
+    info!("Shutdown complete");
+}
+
+
+fn start_sleep_tracking(state_mutex: Arc<Mutex<bool>>, fn_lock_backend: Arc<FnLockBackend>) {
     // keep a timestamp and watch for large jumps.  if a jump is detected, reapply the state
     tokio::spawn(async move {
         let mut last_check = boot_time::Instant::now();
@@ -187,20 +618,37 @@ fn start_sleep_tracking(state_mutex: Arc<Mutex<bool>>, hid_path: String) {
             if elapsed > Duration::from_secs(3) {
                 // likely a sleep/resume event
                 let state = state_mutex.lock().await;
-                println!("Sleep/resume detected, reapplying FnLock state: {}", if *state {"on"} else {"off"});
-                toggle_fn_lock(&hid_path, *state);
+                info!(fn_lock = *state, "Sleep/resume detected, reapplying FnLock state");
+                fn_lock_backend.apply(*state);
+                ambient_light::clear_manual_override();
+                let ambient_running = ambient_light::reapply();
+                if !ambient_running && !kb_illumination::is_disabled() && !backlight_idle::is_faded() {
+                    kb_illumination::restore_levels(&kb_illumination::saved_levels());
+                }
             }
             last_check = now;
         }
     });
 }
 
-fn start_device_thread(device_path: String, config: Arc<ConfigWrapper>, state: Arc<Mutex<bool>>,
-                       hid_device_info: Arc<HidDeviceInfo>, active_paths_mutex: Arc<RwLock<HashSet<String>>>) {
-    tokio::spawn(async move {
-        println!("Opening event device: {}", device_path);
-        let device = Device::open(&device_path)
-            .expect("Failed to open input device");
+// returns false if the device couldn't be opened
+fn start_device_thread(device_path: String, config: Arc<RwLock<ConfigWrapper>>, state: Arc<Mutex<bool>>,
+                       fn_lock_backend: Arc<FnLockBackend>, active_paths_mutex: Arc<RwLock<HashSet<String>>>) -> bool {
+    // opened before spawning so the initial devices are open before privileges are dropped
+    info!(target: "events", device = %device_path, "Opening event device");
+    let mut device = match privileges::open_event_device(&device_path) {
+        Ok(device) => device,
+        Err(e) => {
+            error!(target: "events", device = %device_path, "Failed to open input device: {}", e);
+            return false;
+        }
+    };
+
+    let task = tokio::spawn(async move {
+        let mut emulation = None;
+        if config.read().await.fnlock.enabled && matches!(*fn_lock_backend, FnLockBackend::Emulated) {
+            emulation = EmulatedFnLock::attach(&mut device);
+        }
 
         let mut stream = device.into_event_stream()
             .expect("Failed to create event stream");
@@ -208,18 +656,28 @@ fn start_device_thread(device_path: String, config: Arc<ConfigWrapper>, state: A
         loop {
             let event = stream.next_event().await;
             if event.is_err() {
-                println!("Error reading event, exiting loop: {:?}", event);
+                warn!(target: "events", device = %device_path, "Error reading event, exiting loop: {:?}", event);
                 break;
             }
             if let Ok(ev) = event {
+                let config = config.read().await;
                 if ev.event_type() == EventType::KEY {
+                    debug!(target: "events", device = %device_path, keycode = ?KeyCode::new(ev.code()), value = ev.value(), "Key event");
+                    if ev.value() == 1 {
+                        backlight_idle::notify_activity();
+                    }
+
                     // check for kb_illum_toggle keycode
                     if config.kb_brightness_cycle.enabled
                         && ev.code() == config.kb_brightness_cycle.keycode_enum.unwrap().code()
                         && ev.value() == 1
                     {
-                        println!("kb brightness event");
-                        kb_illumination::cycle();
+                        debug!(target: "events", device = %device_path, keycode = ?KeyCode::new(ev.code()), "Brightness cycle key");
+                        let levels = kb_illumination::cycle(&config.kb_brightness_cycle);
+                        save_backlight_state(&levels);
+                        if config.backlight.ambient.enabled {
+                            ambient_light::set_manual_override(config.backlight.ambient.override_secs);
+                        }
                     }
 
                     // check for fnlock
@@ -228,28 +686,55 @@ fn start_device_thread(device_path: String, config: Arc<ConfigWrapper>, state: A
                         && ev.value() == 1
                     {
                         let mut state = state.lock().await;
-                        println!("Fn key event");
+                        debug!(target: "events", device = %device_path, keycode = ?KeyCode::new(ev.code()), "Fn-Lock key");
                         *state = !*state;
-                        toggle_fn_lock(&hid_device_info.hidraw_device_path, state.clone());
+                        fn_lock_backend.apply(state.clone());
                         save_state(state.clone());
+                        set_status("fnlock_state", if *state { "on" } else { "off" });
                     }
                 } else if ev.event_type() == EventType::SWITCH {
+                    if ev.code() == SwitchCode::SW_LID.0 && ev.value() == 0 {
+                        // lid opened
+                        ambient_light::clear_manual_override();
+                    }
                     if ev.code() == SwitchCode::SW_TABLET_MODE.0 {
                         if config.tablet_kb_backlight_disable.enabled {
                             if ev.value() == 1 {
-                                println!("Tablet mode enabled, disabling keyboard backlight");
+                                info!(target: "events", device = %device_path, "Tablet mode enabled, disabling keyboard backlight");
                                 kb_illumination::disable_toggle(true);
                             } else {
-                                println!("Tablet mode disabled, restoring keyboard backlight");
+                                info!(target: "events", device = %device_path, "Tablet mode disabled, restoring keyboard backlight");
                                 kb_illumination::disable_toggle(false);
                             }
                         }
                     }
                 }
+
+                if let Some(emulation) = emulation.as_mut() {
+                    let fn_lock = *state.lock().await;
+                    emulation.forward(&ev, fn_lock);
+                }
             }
         }
-        println!("Event device {} disconnected, exiting task", device_path);
+        info!(target: "events", device = %device_path, "Event device disconnected, exiting task");
         let mut data = active_paths_mutex.write().await;
         data.remove(&device_path);
+        notify_status(data.len(), &*config.read().await, &fn_lock_backend);
     });
+    let mut device_tasks = DEVICE_TASKS.lock().expect("Failed to lock device tasks");
+    device_tasks.retain(|task| !task.is_finished());
+    device_tasks.push(task);
+    true
+}
+
+// summary line shown by `systemctl status`
+fn notify_status(device_count: usize, config: &ConfigWrapper, fn_lock_backend: &FnLockBackend) {
+    let mut status = format!("{} event device(s)", device_count);
+    if config.bpf.enabled {
+        status.push_str(&format!(", {} remap(s), {} rule(s)", config.bpf.remaps.len(), config.bpf.rules.len()));
+    }
+    if config.fnlock.enabled {
+        status.push_str(&format!(", fn-lock backend {}", fn_lock_backend.name()));
+    }
+    sd_notify::notify(&format!("STATUS={}", status));
 }
\ No newline at end of file
@@ -0,0 +1,446 @@
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, IoSlice, IoSliceMut, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::os::unix::net::UnixStream;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use evdev::Device;
+use nix::sys::socket::{recvmsg, sendmsg, ControlMessage, ControlMessageOwned, MsgFlags};
+use nix::errno::Errno;
+use nix::fcntl::AtFlags;
+use nix::sys::stat::fstatat;
+use nix::unistd::{access, fchownat, setgroups, setresgid, setresuid, AccessFlags, Gid, Uid, User};
+use crate::apkt_config::PrivilegesConfig;
+use tracing::{error, info, warn};
+
+// subcommand the helper is started with
+pub static OPEN_HELPER_COMMAND: &str = "open-helper";
+static EVENT_DEVICE_PREFIX: &str = "/dev/input/event";
+
+// daemon side of the socket to the root helper, set once privileges are dropped
+static HELPER: Mutex<Option<UnixStream>> = Mutex::new(None);
+
+// capabilities that can be kept after dropping root
+static CAPABILITIES: [(&str, u32); 7] = [
+    ("CAP_DAC_OVERRIDE", 1),
+    ("CAP_DAC_READ_SEARCH", 2),
+    ("CAP_NET_ADMIN", 12),
+    ("CAP_SYS_ADMIN", 21),
+    ("CAP_SYS_RESOURCE", 24),
+    ("CAP_PERFMON", 38),
+    ("CAP_BPF", 39),
+];
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x20080522;
+
+// what for_each_thread runs in every thread, and whether any thread failed
+const THREAD_KEEPCAPS: u32 = 1;
+const THREAD_CAPSET: u32 = 2;
+static THREAD_OP: AtomicU32 = AtomicU32::new(0);
+static THREAD_CAPABILITIES: AtomicU32 = AtomicU32::new(0);
+static THREADS_DONE: AtomicUsize = AtomicUsize::new(0);
+static THREAD_FAILED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: Option<u32> = Some(0xC000003E);
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: Option<u32> = Some(0xC00000B7);
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+const AUDIT_ARCH: Option<u32> = None;
+
+// syscalls the daemon has no business making once it is running unprivileged
+static DENIED_SYSCALLS: [libc::c_long; 30] = [
+    libc::SYS_execve,
+    libc::SYS_execveat,
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_chroot,
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    libc::SYS_kexec_load,
+    libc::SYS_kexec_file_load,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_acct,
+    libc::SYS_setuid,
+    libc::SYS_setgid,
+    libc::SYS_setreuid,
+    libc::SYS_setregid,
+    libc::SYS_setresuid,
+    libc::SYS_setresgid,
+    libc::SYS_setgroups,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+];
+
+#[repr(C)]
+struct CapUserHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct CapUserData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+// true if the named capability is in the effective set.  always true for root
+pub fn has_capability(name: &str) -> bool {
+    let Some((_, bit)) = CAPABILITIES.iter().find(|(cap, _)| *cap == name) else {
+        return false;
+    };
+    let Ok(status) = std::fs::read_to_string("/proc/self/status") else {
+        return false;
+    };
+    status.lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|caps| u64::from_str_radix(caps.trim(), 16).ok())
+        .is_some_and(|caps| caps & (1 << bit) != 0)
+}
+
+// loading the struct_ops program needs both
+pub fn bpf_allowed() -> bool {
+    has_capability("CAP_BPF") && has_capability("CAP_PERFMON")
+}
+
+// true if the file can be opened for reading, and writing if asked for
+pub fn can_access(path: &str, write: bool) -> bool {
+    let mode = if write { AccessFlags::R_OK | AccessFlags::W_OK } else { AccessFlags::R_OK };
+    access(path, mode).is_ok()
+}
+
+// open an event device, through the root helper once privileges are dropped
+pub fn open_event_device(path: &str) -> std::io::Result<Device> {
+    let helper = HELPER.lock().expect("Failed to lock open helper");
+    let Some(socket) = helper.as_ref() else {
+        return Device::open(path);
+    };
+
+    (&*socket).write_all(format!("{}\n", path).as_bytes())?;
+    let mut errno = [0u8; 4];
+    let mut iov = [IoSliceMut::new(&mut errno)];
+    let mut cmsg_buffer = nix::cmsg_space!([std::os::fd::RawFd; 1]);
+    let msg = recvmsg::<()>(socket.as_raw_fd(), &mut iov, Some(&mut cmsg_buffer), MsgFlags::empty())?;
+    if msg.bytes == 0 {
+        return Err(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "open helper exited"));
+    }
+    let mut fd = None;
+    for cmsg in msg.cmsgs()? {
+        if let ControlMessageOwned::ScmRights(fds) = cmsg {
+            fd = fds.first().map(|fd| unsafe { OwnedFd::from_raw_fd(*fd) });
+        }
+    }
+
+    let errno = i32::from_ne_bytes(errno);
+    if errno != 0 {
+        return Err(std::io::Error::from_raw_os_error(errno));
+    }
+    let fd = fd.ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "open helper sent no fd"))?;
+    Device::from_fd(fd)
+}
+
+// start the helper that opens event devices plugged in after privileges are dropped.  it is this
+// binary started again with the open-helper subcommand, talking over a socket pair on its stdin
+fn start_open_helper() -> std::io::Result<()> {
+    let (daemon_end, helper_end) = UnixStream::pair()?;
+    let exe = std::env::current_exe()?;
+    Command::new(exe)
+        .arg(OPEN_HELPER_COMMAND)
+        .stdin(Stdio::from(OwnedFd::from(helper_end)))
+        .spawn()?;
+    *HELPER.lock().expect("Failed to lock open helper") = Some(daemon_end);
+    Ok(())
+}
+
+// main loop of the root helper.  reads one path per line and answers with an errno, plus the fd
+// when the open succeeded.  exits when the daemon closes its end of the socket
+pub fn run_open_helper() -> Result<(), Box<dyn std::error::Error>> {
+    // stdin is our end of the socket pair
+    let socket = unsafe { UnixStream::from_raw_fd(0) };
+    install_seccomp_filter();
+
+    let mut reader = BufReader::new(&socket);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let path = line.trim();
+
+        // only hand out event devices, nothing else this process could open as root
+        let is_event_device = path.strip_prefix(EVENT_DEVICE_PREFIX)
+            .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()));
+        let file = if is_event_device {
+            std::fs::OpenOptions::new().read(true).write(true).open(path)
+                .or_else(|_| std::fs::File::open(path))
+        } else {
+            eprintln!("Open helper: refusing to open {}", path);
+            Err(std::io::Error::from_raw_os_error(libc::EACCES))
+        };
+
+        let errno = match &file {
+            Ok(_) => 0i32,
+            Err(e) => e.raw_os_error().unwrap_or(libc::EIO),
+        }.to_ne_bytes();
+        let iov = [IoSlice::new(&errno)];
+        let fds = file.as_ref().map(|file| vec![file.as_raw_fd()]).unwrap_or_default();
+        let cmsgs = if fds.is_empty() { vec![] } else { vec![ControlMessage::ScmRights(&fds)] };
+        sendmsg::<()>(socket.as_raw_fd(), &iov, &cmsgs, MsgFlags::empty(), None)?;
+    }
+}
+
+// switch to the configured user, keeping only the configured capabilities.  everything that needs
+// root (hidraw, event devices, leds, bpf) must be opened before this is called
+pub fn drop_privileges(config: &PrivilegesConfig, owned_files: &[(&str, &[&str])]) {
+    if !nix::unistd::geteuid().is_root() {
+        info!("Not running as root, nothing to drop");
+        return;
+    }
+    let user = User::from_name(&config.user)
+        .expect("Failed to look up privileges.user")
+        .unwrap_or_else(|| panic!("User {} from privileges.user does not exist", config.user));
+    let mut keep: u32 = 0;
+    for name in &config.keep_capabilities {
+        let (_, bit) = CAPABILITIES.iter().find(|(cap, _)| cap == name)
+            .unwrap_or_else(|| panic!("Unknown capability in privileges.keep_capabilities: {}", name));
+        keep |= 1 << bit;
+    }
+
+    if let Err(e) = start_open_helper() {
+        error!("Failed to start open helper, new event devices can't be opened: {}", e);
+    }
+
+    // state and status files are rewritten after the drop
+    for (dir, files) in owned_files {
+        if let Err(e) = chown_owned_files(dir, files, user.uid, user.gid) {
+            warn!("Unable to hand {} to {}: {}", dir, config.user, e);
+        }
+    }
+
+    // glibc applies setresuid to every thread of the process, but PR_SET_KEEPCAPS and capset only
+    // change the thread calling them.  both are run in every thread so tasks keep the capabilities on
+    // any runtime worker, as do the threads started before this.  threads started later inherit them.
+    // with nothing to keep, setresuid clears the capabilities of every thread by itself
+    THREAD_CAPABILITIES.store(keep, Ordering::SeqCst);
+    if keep != 0 && !for_each_thread(THREAD_KEEPCAPS) {
+        panic!("Failed to set PR_SET_KEEPCAPS in every thread");
+    }
+    setgroups(&[user.gid]).expect("Failed to set supplementary groups");
+    setresgid(user.gid, user.gid, user.gid).expect("Failed to switch group");
+    setresuid(user.uid, user.uid, user.uid).expect("Failed to switch user");
+    if keep != 0 && !for_each_thread(THREAD_CAPSET) {
+        panic!("Failed to set capabilities in every thread");
+    }
+    info!(user = %config.user, uid = user.uid.as_raw(), "Dropped privileges, keeping {:?}", config.keep_capabilities);
+
+    if config.seccomp {
+        install_seccomp_filter();
+    }
+}
+
+// chown a directory and the named files in it, as root.  the directory belongs to the daemon user
+// after the first run, so nothing is followed: the directory is opened with O_NOFOLLOW and only
+// regular files with a single link are changed.  directories owned by someone else, or writable by
+// everyone like /tmp, are refused so a misconfigured path can't hand them over
+fn chown_owned_files(dir: &str, files: &[&str], uid: Uid, gid: Gid) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let directory = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_DIRECTORY | libc::O_NOFOLLOW)
+        .open(dir)
+        .map_err(|e| e.to_string())?;
+    let metadata = directory.metadata().map_err(|e| e.to_string())?;
+    if metadata.uid() != 0 && metadata.uid() != uid.as_raw() {
+        return Err(format!("owned by uid {}, not root or the daemon user", metadata.uid()));
+    }
+    if metadata.mode() & 0o002 != 0 {
+        return Err("writable by everyone".to_string());
+    }
+    std::os::unix::fs::fchown(&directory, Some(uid.as_raw()), Some(gid.as_raw())).map_err(|e| e.to_string())?;
+
+    for file in files {
+        let stat = match fstatat(Some(directory.as_raw_fd()), *file, AtFlags::AT_SYMLINK_NOFOLLOW) {
+            Ok(stat) => stat,
+            Err(Errno::ENOENT) => continue,
+            Err(e) => return Err(format!("{}: {}", file, e)),
+        };
+        if stat.st_mode & libc::S_IFMT != libc::S_IFREG || stat.st_nlink != 1 {
+            warn!("Not handing {}/{} to the daemon user, it is not a plain file", dir, file);
+            continue;
+        }
+        fchownat(Some(directory.as_raw_fd()), *file, Some(uid), Some(gid), AtFlags::AT_SYMLINK_NOFOLLOW)
+            .map_err(|e| format!("{}: {}", file, e))?;
+    }
+    Ok(())
+}
+
+// only the calling thread's effective and permitted sets
+fn set_thread_capabilities(keep: u32) -> bool {
+    let header = CapUserHeader { version: LINUX_CAPABILITY_VERSION_3, pid: 0 };
+    let mut data = [CapUserData::default(); 2];
+    data[0].effective = keep;
+    data[0].permitted = keep;
+    unsafe { libc::syscall(libc::SYS_capset, &header, data.as_mut_ptr()) == 0 }
+}
+
+// async signal safe, it also runs from thread_op_handler
+fn run_thread_op() -> bool {
+    match THREAD_OP.load(Ordering::SeqCst) {
+        // keep the permitted set across the uid change so capset can pick from it
+        THREAD_KEEPCAPS => unsafe { libc::prctl(libc::PR_SET_KEEPCAPS, 1, 0, 0, 0) == 0 },
+        _ => set_thread_capabilities(THREAD_CAPABILITIES.load(Ordering::SeqCst)),
+    }
+}
+
+extern "C" fn thread_op_handler(_signal: libc::c_int) {
+    if !run_thread_op() {
+        THREAD_FAILED.store(true, Ordering::SeqCst);
+    }
+    THREADS_DONE.fetch_add(1, Ordering::SeqCst);
+}
+
+// run a per thread operation in every thread of the process, like glibc does for setresuid: this
+// thread directly, the others from a signal handler.  repeated until no new threads show up, in case
+// one was started in the meantime.  false if it failed or a thread didn't answer
+fn for_each_thread(op: u32) -> bool {
+    THREAD_OP.store(op, Ordering::SeqCst);
+    THREAD_FAILED.store(false, Ordering::SeqCst);
+    let signal = libc::SIGRTMIN();
+    // left installed, a late signal must not hit the default action and kill the process
+    unsafe { libc::signal(signal, thread_op_handler as *const () as libc::sighandler_t) };
+
+    let pid = std::process::id() as libc::c_long;
+    let own_tid = unsafe { libc::syscall(libc::SYS_gettid) };
+    let mut ok = run_thread_op();
+    let mut handled = HashSet::from([own_tid]);
+    loop {
+        let tids: Vec<libc::c_long> = std::fs::read_dir("/proc/self/task").into_iter().flatten().flatten()
+            .filter_map(|entry| entry.file_name().to_string_lossy().parse().ok())
+            .filter(|tid| !handled.contains(tid))
+            .collect();
+        if tids.is_empty() {
+            break;
+        }
+        THREADS_DONE.store(0, Ordering::SeqCst);
+        let mut signalled = 0;
+        for tid in tids {
+            handled.insert(tid);
+            // ESRCH means it exited since the listing
+            if unsafe { libc::syscall(libc::SYS_tgkill, pid, tid, signal) } == 0 {
+                signalled += 1;
+            }
+        }
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while THREADS_DONE.load(Ordering::SeqCst) < signalled && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        if THREADS_DONE.load(Ordering::SeqCst) < signalled {
+            error!("{} thread(s) didn't change their capabilities", signalled - THREADS_DONE.load(Ordering::SeqCst));
+            ok = false;
+        }
+    }
+    ok && !THREAD_FAILED.load(Ordering::SeqCst)
+}
+
+fn stmt(code: u32, k: u32) -> libc::sock_filter {
+    libc::sock_filter { code: code as u16, jt: 0, jf: 0, k }
+}
+
+fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+    libc::sock_filter { code: code as u16, jt, jf, k }
+}
+
+// deny the syscalls in DENIED_SYSCALLS with EPERM, in every thread of the process
+fn install_seccomp_filter() {
+    let Some(audit_arch) = AUDIT_ARCH else {
+        warn!("Seccomp filter not supported on this architecture");
+        return;
+    };
+    let mut filter = vec![
+        stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 4), // arch
+        jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, audit_arch, 1, 0),
+        stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_KILL_PROCESS),
+        stmt(libc::BPF_LD | libc::BPF_W | libc::BPF_ABS, 0), // syscall number
+        // x32 syscalls on x86_64
+        jump(libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K, 0x40000000, 0, 1),
+        stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ERRNO | libc::EPERM as u32),
+    ];
+    for syscall in DENIED_SYSCALLS {
+        filter.push(jump(libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K, syscall as u32, 0, 1));
+        filter.push(stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ERRNO | libc::EPERM as u32));
+    }
+    filter.push(stmt(libc::BPF_RET | libc::BPF_K, libc::SECCOMP_RET_ALLOW));
+
+    let program = libc::sock_fprog { len: filter.len() as u16, filter: filter.as_mut_ptr() };
+    unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) != 0 {
+            panic!("Failed to set PR_SET_NO_NEW_PRIVS: {}", std::io::Error::last_os_error());
+        }
+        let res = libc::syscall(
+            libc::SYS_seccomp,
+            libc::SECCOMP_SET_MODE_FILTER,
+            libc::SECCOMP_FILTER_FLAG_TSYNC,
+            &program,
+        );
+        if res != 0 {
+            panic!("Failed to install seccomp filter: {}", std::io::Error::last_os_error());
+        }
+    }
+    info!("Seccomp filter installed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn current_user() -> (Uid, Gid) {
+        (nix::unistd::getuid(), nix::unistd::getgid())
+    }
+
+    #[test]
+    fn chowns_the_directory_and_its_files() {
+        let temp = TempDir::new("owned");
+        let state = temp.write("state/state.toml", "version = 1\n");
+        let (uid, gid) = current_user();
+        assert_eq!(chown_owned_files(&format!("{}/state", temp.path()), &["state.toml", "state.toml.tmp"], uid, gid), Ok(()));
+        assert_eq!(std::fs::metadata(state).unwrap().uid(), uid.as_raw());
+    }
+
+    #[test]
+    fn refuses_world_writable_directories() {
+        let temp = TempDir::new("owned");
+        let dir = temp.create_dir("shared");
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o1777)).unwrap();
+        let (uid, gid) = current_user();
+        assert!(chown_owned_files(&dir, &[], uid, gid).is_err());
+    }
+
+    #[test]
+    fn follows_no_symlinks() {
+        let temp = TempDir::new("owned");
+        let target = temp.write("target/secret", "secret\n");
+        let dir = temp.create_dir("state");
+        std::os::unix::fs::symlink(&target, format!("{}/state.toml", dir)).unwrap();
+        std::os::unix::fs::symlink(format!("{}/target", temp.path()), format!("{}/linked", temp.path())).unwrap();
+        let (uid, gid) = current_user();
+        // the link is skipped, not followed
+        assert_eq!(chown_owned_files(&dir, &["state.toml"], uid, gid), Ok(()));
+        assert!(chown_owned_files(&format!("{}/linked", temp.path()), &["secret"], uid, gid).is_err());
+    }
+}
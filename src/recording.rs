@@ -0,0 +1,389 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::fd::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use evdev::uinput::VirtualDevice;
+use evdev::{AttributeSet, Device, EventType, InputEvent, KeyCode, SwitchCode};
+use crate::bpf_loader;
+use crate::hid::{event_nodes, find_vendor_interface, hidraw_node};
+use crate::remap_model::{parse_report, LogEntry};
+use crate::uhid::VirtualKeyboard;
+
+// recordings are text, one line per event: seconds since the start, the kind of event, then its
+// fields.  lines starting with # are comments
+//   0.000000 descriptor 0631ff0976a101...
+//   0.000000 evdev-device /dev/input/event5 Asus Keyboard
+//   1.234567 hidraw 5a4e00000000
+//   1.234601 bpf report_id=0x5a offset=1 original=0x4e new=0x5c rule=- reason=-
+//   1.234890 evdev /dev/input/event5 1 203 1
+static HEADER: &str = "# asus-px-keyboard-tool recording";
+
+// set by Ctrl-C, so the bpf tap is turned off again before exiting
+static STOP: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn stop_recording(_signal: libc::c_int) {
+    STOP.store(true, Ordering::Relaxed);
+}
+
+#[derive(Debug, PartialEq)]
+enum Record {
+    Descriptor(Vec<u8>),
+    Hidraw(Vec<u8>),
+    // the name is used for the uinput device on replay
+    EvdevDevice { path: String, name: String },
+    Evdev { path: String, event_type: u16, code: u16, value: i32 },
+    // only informational, replay doesn't need it
+    Bpf,
+}
+
+struct Line {
+    time: f64,
+    record: Record,
+}
+
+struct Recorder {
+    file: Mutex<File>,
+    started: Instant,
+}
+
+impl Recorder {
+    // written and flushed right away so nothing is lost when record is interrupted
+    fn write(&self, kind: &str, fields: &str) -> std::io::Result<()> {
+        let line = format!("{:.6} {} {}\n", self.started.elapsed().as_secs_f64(), kind, fields);
+        self.file.lock().expect("Failed to lock recording").write_all(line.as_bytes())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn bpf_fields(entry: &LogEntry) -> String {
+    format!(
+        "report_id={:#04x} offset={} original={:#04x} new={:#04x} rule={} reason={}",
+        entry.report_id,
+        entry.offset,
+        entry.original,
+        if entry.remapped { entry.new } else { entry.original },
+        entry.rule.map(|rule| rule.to_string()).unwrap_or("-".to_string()),
+        entry.reason.map(|reason| reason.name()).unwrap_or("-"),
+    )
+}
+
+// record <file> [event_device...]: the vendor interface's report descriptor and reports, the bpf
+// program's log entries and the events of the given event devices (by default the ones hid-asus
+// created for the vendor interface), until interrupted
+pub fn run_record(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let output_path = args.first().ok_or("record needs an output file")?;
+    let bus_path = find_vendor_interface().ok_or("No matching HID device found")?;
+    let hidraw_path = hidraw_node(&bus_path).ok_or("No hidraw node for the vendor interface (modprobe hidraw)")?;
+    let descriptor = std::fs::read(format!("{}/report_descriptor", bus_path))
+        .map_err(|e| format!("Unable to read the report descriptor of {}: {}", bus_path, e))?;
+    let mut event_paths: Vec<String> = args[1..].to_vec();
+    if event_paths.is_empty() {
+        event_paths = event_nodes(&bus_path);
+    }
+
+    let mut file = File::create(output_path).map_err(|e| format!("Unable to create {}: {}", output_path, e))?;
+    writeln!(file, "{}", HEADER)?;
+    writeln!(file, "# device {} {}", bus_path, hidraw_path)?;
+    let recorder = Arc::new(Recorder { file: Mutex::new(file), started: Instant::now() });
+    recorder.write("descriptor", &hex(&descriptor))?;
+
+    for path in event_paths {
+        let mut device = Device::open(&path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+        recorder.write("evdev-device", &format!("{} {}", path, device.name().unwrap_or("unknown")))?;
+        let recorder = Arc::clone(&recorder);
+        std::thread::spawn(move || loop {
+            let events = match device.fetch_events() {
+                Ok(events) => events,
+                Err(e) => {
+                    eprintln!("Stopped recording {}: {}", path, e);
+                    return;
+                }
+            };
+            for event in events {
+                // replay sends its own syn reports
+                if event.event_type() == EventType::SYNCHRONIZATION {
+                    continue;
+                }
+                let fields = format!("{} {} {} {}", path, event.event_type().0, event.code(), event.value());
+                if recorder.write("evdev", &fields).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    let bpf_recorder = Arc::clone(&recorder);
+    let bpf_thread = std::thread::spawn(move || {
+        let result = bpf_loader::poll_tap_events(&STOP, |entry| bpf_recorder.write("bpf", &bpf_fields(entry)));
+        if let Err(e) = result {
+            eprintln!("Not recording BPF events: {}", e);
+        }
+    });
+
+    let mut hidraw = File::open(&hidraw_path).map_err(|e| format!("Unable to open {}: {}", hidraw_path, e))?;
+    unsafe {
+        libc::signal(libc::SIGINT, stop_recording as *const () as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, stop_recording as *const () as libc::sighandler_t);
+    }
+    println!("Recording {} to {}, press Ctrl-C to stop", hidraw_path, output_path);
+    let mut report = [0u8; 64];
+    let mut result = Ok(());
+    while !STOP.load(Ordering::Relaxed) {
+        // wake up regularly to notice Ctrl-C
+        let mut poll_fd = libc::pollfd { fd: hidraw.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        if unsafe { libc::poll(&mut poll_fd, 1, 200) } <= 0 {
+            continue;
+        }
+        let written = hidraw.read(&mut report).and_then(|len| recorder.write("hidraw", &hex(&report[..len])));
+        if let Err(e) = written {
+            result = Err(e.into());
+            break;
+        }
+    }
+    STOP.store(true, Ordering::Relaxed);
+    let _ = bpf_thread.join();
+    println!("Recording stopped");
+    result
+}
+
+fn parse_line(line: &str) -> Result<Option<Line>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut parts = line.splitn(3, ' ');
+    let time = parts.next().unwrap_or("").parse::<f64>()
+        .map_err(|e| format!("Invalid time in \"{}\": {}", line, e))?;
+    let kind = parts.next().ok_or(format!("Missing event kind in \"{}\"", line))?;
+    let fields = parts.next().unwrap_or("");
+    let record = match kind {
+        "descriptor" => Record::Descriptor(parse_report(fields)?),
+        "hidraw" => Record::Hidraw(parse_report(fields)?),
+        "evdev-device" => {
+            let (path, name) = fields.split_once(' ').unwrap_or((fields, "unknown"));
+            Record::EvdevDevice { path: path.to_string(), name: name.to_string() }
+        }
+        "evdev" => {
+            let values: Vec<&str> = fields.split(' ').collect();
+            let [path, event_type, code, value] = values[..] else {
+                return Err(format!("Expected \"evdev <path> <type> <code> <value>\", got \"{}\"", line));
+            };
+            let invalid = |e: std::num::ParseIntError| format!("Invalid evdev event \"{}\": {}", line, e);
+            Record::Evdev {
+                path: path.to_string(),
+                event_type: event_type.parse().map_err(invalid)?,
+                code: code.parse().map_err(invalid)?,
+                value: value.parse().map_err(invalid)?,
+            }
+        }
+        "bpf" => Record::Bpf,
+        other => return Err(format!("Unknown event kind {} in \"{}\"", other, line)),
+    };
+    Ok(Some(Line { time, record }))
+}
+
+fn read_recording(path: &str) -> Result<Vec<Line>, Box<dyn std::error::Error>> {
+    let file = File::open(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+    let mut lines = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Some(line) = parse_line(&line?)? {
+            lines.push(line);
+        }
+    }
+    Ok(lines)
+}
+
+// sleep until `time` seconds after `started`
+fn wait_until(started: Instant, time: f64) {
+    let target = started + Duration::from_secs_f64(time.max(0.0));
+    let now = Instant::now();
+    if target > now {
+        std::thread::sleep(target - now);
+    }
+}
+
+// replay <file> [hidraw|evdev]: feed a recording back with its original timing.  hidraw sends the
+// reports from a uhid keyboard with the recorded report descriptor, evdev the input events from
+// uinput devices named like the recorded ones
+pub fn run_replay(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let input_path = args.first().ok_or("replay needs a recording")?;
+    let lines = read_recording(input_path)?;
+    match args.get(1).map(|mode| mode.as_str()).unwrap_or("hidraw") {
+        "hidraw" => replay_hidraw(&lines),
+        "evdev" => replay_evdev(&lines),
+        other => Err(format!("Invalid replay mode {}: expected hidraw or evdev", other).into()),
+    }
+}
+
+fn replay_hidraw(lines: &[Line]) -> Result<(), Box<dyn std::error::Error>> {
+    let descriptor = lines.iter().find_map(|line| match &line.record {
+        Record::Descriptor(descriptor) => Some(descriptor.clone()),
+        _ => None,
+    });
+    let mut keyboard = match descriptor {
+        Some(descriptor) => VirtualKeyboard::create_with_descriptor(&descriptor),
+        None => {
+            println!("No report descriptor in the recording, using the PX keyboard's");
+            VirtualKeyboard::create()
+        }
+    }.map_err(|e| format!("Unable to create uhid device (needs root or uhid access): {}", e))?;
+    let mut injector = keyboard.injector()?;
+    let event_thread = std::thread::spawn(move || {
+        while let Ok(true) = keyboard.process_event() {}
+    });
+
+    // give the kernel and hid-asus time to bind before the first report
+    std::thread::sleep(Duration::from_millis(500));
+    let started = Instant::now();
+    let mut count = 0;
+    for line in lines {
+        if let Record::Hidraw(report) = &line.record {
+            wait_until(started, line.time);
+            injector.send_input(report)?;
+            count += 1;
+        }
+    }
+    println!("Replayed {} reports", count);
+    injector.destroy()?;
+    let _ = event_thread.join();
+    Ok(())
+}
+
+fn replay_evdev(lines: &[Line]) -> Result<(), Box<dyn std::error::Error>> {
+    // the keys and switches each recorded device used
+    let mut keys: BTreeMap<&str, AttributeSet<KeyCode>> = BTreeMap::new();
+    let mut switches: BTreeMap<&str, AttributeSet<SwitchCode>> = BTreeMap::new();
+    for line in lines {
+        if let Record::Evdev { path, event_type, code, .. } = &line.record {
+            if *event_type == EventType::KEY.0 {
+                keys.entry(path).or_default().insert(KeyCode::new(*code));
+            } else if *event_type == EventType::SWITCH.0 {
+                switches.entry(path).or_default().insert(SwitchCode(*code));
+            }
+        }
+    }
+
+    let mut devices: BTreeMap<&str, VirtualDevice> = BTreeMap::new();
+    for line in lines {
+        let Record::EvdevDevice { path, name } = &line.record else {
+            continue;
+        };
+        if !keys.contains_key(path.as_str()) && !switches.contains_key(path.as_str()) {
+            continue; // nothing to replay
+        }
+        let mut builder = VirtualDevice::builder()?.name(name);
+        if let Some(keys) = keys.get(path.as_str()) {
+            builder = builder.with_keys(keys)?;
+        }
+        if let Some(switches) = switches.get(path.as_str()) {
+            builder = builder.with_switches(switches)?;
+        }
+        devices.insert(path, builder.build()?);
+        println!("Created {} for {}", name, path);
+    }
+
+    // give udev and the daemon time to pick up the devices
+    std::thread::sleep(Duration::from_millis(500));
+    let started = Instant::now();
+    let mut count = 0;
+    for line in lines {
+        let Record::Evdev { path, event_type, code, value } = &line.record else {
+            continue;
+        };
+        let Some(device) = devices.get_mut(path.as_str()) else {
+            continue;
+        };
+        if *event_type != EventType::KEY.0 && *event_type != EventType::SWITCH.0 {
+            continue;
+        }
+        wait_until(started, line.time);
+        device.emit(&[InputEvent::new(*event_type, *code, *value)])?;
+        count += 1;
+    }
+    println!("Replayed {} events", count);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remap_model::DropReason;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn hex_is_lowercase_without_separators() {
+        assert_eq!(hex(&[0x5a, 0x4e, 0x00, 0xff]), "5a4e00ff");
+        assert_eq!(hex(&[]), "");
+    }
+
+    #[test]
+    fn formats_bpf_entries() {
+        let remapped = LogEntry { original: 0x4e, remapped: true, new: 0x5c, report_id: 0x5a, offset: 1, rule: None, reason: None };
+        assert_eq!(bpf_fields(&remapped), "report_id=0x5a offset=1 original=0x4e new=0x5c rule=- reason=-");
+        let dropped = LogEntry { original: 0x38, remapped: false, new: 0, report_id: 0x5a, offset: 1, rule: Some(2), reason: Some(DropReason::Debounce) };
+        assert_eq!(bpf_fields(&dropped), format!("report_id=0x5a offset=1 original=0x38 new=0x38 rule=2 reason={}", DropReason::Debounce.name()));
+    }
+
+    #[test]
+    fn reads_back_what_was_recorded() {
+        let dir = TempDir::new("recording");
+        let path = format!("{}/keys.rec", dir.path());
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "{}", HEADER).unwrap();
+        let recorder = Recorder { file: Mutex::new(file), started: Instant::now() };
+        let entry = LogEntry { original: 0x4e, remapped: true, new: 0x5c, report_id: 0x5a, offset: 1, rule: None, reason: None };
+        recorder.write("descriptor", &hex(&[0x06, 0x31, 0xff, 0x09, 0x76])).unwrap();
+        recorder.write("evdev-device", "/dev/input/event5 Asus Keyboard").unwrap();
+        recorder.write("hidraw", &hex(&[0x5a, 0x4e, 0x00, 0x00, 0x00, 0x00])).unwrap();
+        recorder.write("bpf", &bpf_fields(&entry)).unwrap();
+        recorder.write("evdev", "/dev/input/event5 1 203 -1").unwrap();
+
+        let lines = read_recording(&path).unwrap();
+        let records: Vec<&Record> = lines.iter().map(|line| &line.record).collect();
+        assert_eq!(records, vec![
+            &Record::Descriptor(vec![0x06, 0x31, 0xff, 0x09, 0x76]),
+            &Record::EvdevDevice { path: "/dev/input/event5".to_string(), name: "Asus Keyboard".to_string() },
+            &Record::Hidraw(vec![0x5a, 0x4e, 0x00, 0x00, 0x00, 0x00]),
+            &Record::Bpf,
+            &Record::Evdev { path: "/dev/input/event5".to_string(), event_type: 1, code: 203, value: -1 },
+        ]);
+        assert!(lines.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        assert!(parse_line("# device /sys/bus/hid/devices/0003:0B05:19B6.0001 /dev/hidraw0").unwrap().is_none());
+        assert!(parse_line("   ").unwrap().is_none());
+    }
+
+    #[test]
+    fn device_name_defaults_to_unknown() {
+        let line = parse_line("0.5 evdev-device /dev/input/event7").unwrap().unwrap();
+        assert_eq!(line.time, 0.5);
+        assert_eq!(line.record, Record::EvdevDevice { path: "/dev/input/event7".to_string(), name: "unknown".to_string() });
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for line in [
+            "soon hidraw 5a4e",
+            "1.0",
+            "1.0 keypress 5a4e",
+            "1.0 hidraw 5a4",
+            "1.0 hidraw zz",
+            "1.0 descriptor",
+            "1.0 evdev /dev/input/event5 1 203",
+            "1.0 evdev /dev/input/event5 1 203 1 0",
+            "1.0 evdev /dev/input/event5 key 203 1",
+            "1.0 evdev /dev/input/event5 1 70000 1",
+        ] {
+            assert!(parse_line(line).is_err(), "accepted {:?}", line);
+        }
+    }
+}
@@ -0,0 +1,742 @@
+use std::collections::BTreeMap;
+use crate::apkt_config::{get_config, BpfConfig, Debounce, Remap, RemapRule};
+use tracing::debug;
+
+// the same transformation modify_hid_event in bpf/hid_modify.bpf.c applies, for checking remaps
+// without HID-BPF.  keep the two in sync
+
+// hotkey reports shorter than this are never remapped
+pub static MIN_REPORT_LEN: usize = 6;
+// max_entries of remap_map unless bpf.map_size is set.  it's sized before loading, at least this
+// big so remaps can be added on reload
+pub static DEFAULT_REMAP_MAP_SIZE: usize = 32;
+// one entry per scancode, also max_entries of remap_array
+pub static MAX_REMAP_MAP_SIZE: usize = 256;
+// MAX_RULES, RULE_REPORT_BYTES, MAX_REPORT_IDS and MAX_LOG_IGNORE in bpf/hid_modify.bpf.h
+pub static MAX_RULES: usize = 32;
+pub static RULE_REPORT_BYTES: usize = 16;
+pub static MAX_REPORT_IDS: usize = 8;
+pub static MAX_LOG_IGNORE: usize = 32;
+// longest debounce window, anything longer would eat real key presses
+pub static MAX_DEBOUNCE_MS: u32 = 1000;
+// offset of the scancode in hotkey reports
+static SCANCODE_OFFSET: u8 = 1;
+
+// what the program writes to event_rb for every key press and every rule that matched
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogEntry {
+    pub original: u8,
+    pub remapped: bool,
+    pub new: u8,
+    pub report_id: u8,
+    pub offset: u8,
+    // index of the rule that matched, None for scancode remaps
+    pub rule: Option<u8>,
+    // set when the report was dropped
+    pub reason: Option<DropReason>,
+}
+
+// REASON_* in bpf/hid_modify.bpf.h
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DropReason {
+    Debounce,
+}
+
+impl DropReason {
+    pub fn from_code(code: i32) -> Option<DropReason> {
+        match code {
+            1 => Some(DropReason::Debounce),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DropReason::Debounce => "debounce",
+        }
+    }
+}
+
+// a validated [[bpf.rules]] entry, laid out like struct remap_rule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rule {
+    pub report_id: u8,
+    pub offset: u8,
+    pub value: u8,
+    pub mask: u8,
+    pub to: u8,
+}
+
+impl Rule {
+    // bytes of an enabled struct remap_rule
+    pub fn to_bytes(self) -> [u8; 6] {
+        [1, self.report_id, self.offset, self.value, self.mask, self.to]
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RemapTable {
+    remaps: BTreeMap<u32, u32>,
+    rules: Vec<Rule>,
+    report_ids: Vec<u8>,
+    log_ignore: Vec<u8>,
+    // debounce window in ns per scancode
+    debounce: BTreeMap<u8, u64>,
+    // time of the last press let through, per debounced scancode
+    last_press: BTreeMap<u8, u64>,
+}
+
+impl RemapTable {
+    pub fn new(config: &BpfConfig) -> Result<RemapTable, String> {
+        validate_remaps(&config.remaps, remap_map_size(config)?)?;
+        Ok(RemapTable {
+            remaps: config.remaps.iter().map(|remap| (remap.from, remap.to)).collect(),
+            rules: parse_rules(&config.rules)?,
+            report_ids: parse_report_ids(&config.report_ids)?,
+            log_ignore: parse_log_ignore(&config.log_ignore)?,
+            debounce: parse_debounce(&config.debounce)?.into_iter().collect(),
+            last_press: BTreeMap::new(),
+        })
+    }
+
+    // reports that carry a scancode in byte 1
+    pub fn is_hotkey_report(&self, report_id: u8) -> bool {
+        self.report_ids.contains(&report_id)
+    }
+
+    pub fn is_log_ignored(&self, scancode: u8) -> bool {
+        self.log_ignore.contains(&scancode)
+    }
+
+    // rewrite a raw report in place.  returns the log entry, None if the program would skip it.  an
+    // entry with a reason means the report is dropped.  now_ns is a monotonic time for debouncing
+    pub fn apply(&mut self, report: &mut [u8], now_ns: u64) -> Option<LogEntry> {
+        if report.is_empty() {
+            return None;
+        }
+        // key releases (scancode 0) are passed through
+        let hotkey = report.len() >= MIN_REPORT_LEN && self.is_hotkey_report(report[0]) && report[1] != 0;
+        let mut entry = LogEntry {
+            original: *report.get(1).unwrap_or(&0),
+            remapped: false,
+            new: 0,
+            report_id: report[0],
+            offset: SCANCODE_OFFSET,
+            rule: None,
+            reason: None,
+        };
+        // debounced before the rules.  dropped presses are always logged, even for log_ignore
+        // scancodes
+        if hotkey && self.debounce(entry.original, now_ns) {
+            entry.reason = Some(DropReason::Debounce);
+            return Some(entry);
+        }
+        // rule hits and remaps of ignored scancodes still happen, they're just not logged
+        let ignored = hotkey && self.is_log_ignored(entry.original);
+        if let Some(rule_entry) = self.apply_rules(report) {
+            return if ignored { None } else { Some(rule_entry) };
+        }
+
+        if !hotkey {
+            return None;
+        }
+        if let Some(new) = self.remaps.get(&(report[1] as u32)) {
+            entry.remapped = true;
+            entry.new = *new as u8;
+            report[1] = *new as u8;
+        }
+        if ignored {
+            return None;
+        }
+        Some(entry)
+    }
+
+    // true if the press came within the debounce window of the last one let through
+    fn debounce(&mut self, scancode: u8, now_ns: u64) -> bool {
+        let Some(window) = self.debounce.get(&scancode) else {
+            return false;
+        };
+        if let Some(last) = self.last_press.get(&scancode) {
+            if now_ns.saturating_sub(*last) < *window {
+                return true;
+            }
+        }
+        self.last_press.insert(scancode, now_ns);
+        false
+    }
+
+    // the first rule matching the report rewrites it, the scancode remaps are skipped then.  like the
+    // program on a device whose reports are at least RULE_REPORT_BYTES long
+    fn apply_rules(&self, report: &mut [u8]) -> Option<LogEntry> {
+        for (index, rule) in self.rules.iter().enumerate() {
+            let offset = rule.offset as usize;
+            if rule.report_id != report[0] || offset >= RULE_REPORT_BYTES || offset >= report.len() {
+                continue;
+            }
+            let original = report[offset];
+            if original & rule.mask != rule.value {
+                continue;
+            }
+            report[offset] = (original & !rule.mask) | (rule.to & rule.mask);
+            return Some(LogEntry {
+                original,
+                remapped: true,
+                new: report[offset],
+                report_id: report[0],
+                offset: rule.offset,
+                rule: Some(index as u8),
+                reason: None,
+            });
+        }
+        None
+    }
+}
+
+// max_entries the remap map is loaded with.  an empty list still gets a map, the program needs one
+pub fn remap_map_size(config: &BpfConfig) -> Result<usize, String> {
+    match config.map_type.as_str() {
+        "array" => return Ok(MAX_REMAP_MAP_SIZE),
+        "hash" => {}
+        other => return Err(format!("Invalid bpf.map_type {}: expected \"hash\" or \"array\"", other)),
+    }
+    let size = match config.map_size {
+        Some(size) => size as usize,
+        None => config.remaps.len().max(DEFAULT_REMAP_MAP_SIZE),
+    };
+    if size == 0 || size > MAX_REMAP_MAP_SIZE {
+        return Err(format!("Invalid bpf.map_size {}: must be 1 to {}", size, MAX_REMAP_MAP_SIZE));
+    }
+    Ok(size)
+}
+
+// what a remap map of map_size entries can actually hold and match.  used for the config and
+// before filling the map
+pub fn validate_remaps(remaps: &[Remap], map_size: usize) -> Result<(), String> {
+    if remaps.len() > map_size {
+        return Err(format!("Too many bpf remaps: {} (the remap map holds {}, raise bpf.map_size)", remaps.len(), map_size));
+    }
+    let mut seen = BTreeMap::new();
+    for remap in remaps {
+        if remap.from == 0 || remap.from > 0xff {
+            return Err(format!("Invalid bpf remap from {:#x}: scancodes are 0x01 to 0xff", remap.from));
+        }
+        if remap.to > 0xff {
+            return Err(format!("Invalid bpf remap to {:#x}: scancodes are 0x00 to 0xff", remap.to));
+        }
+        if let Some(to) = seen.insert(remap.from, remap.to) {
+            return Err(format!("Scancode {:#04x} is remapped twice (to {:#04x} and {:#04x})", remap.from, to, remap.to));
+        }
+    }
+    Ok(())
+}
+
+// everything in [bpf] the maps have to hold.  used when loading the config
+pub fn validate_bpf_config(config: &BpfConfig) -> Result<(), String> {
+    RemapTable::new(config).map(|_| ())
+}
+
+pub fn parse_report_ids(report_ids: &[u32]) -> Result<Vec<u8>, String> {
+    if report_ids.len() > MAX_REPORT_IDS {
+        return Err(format!("Too many bpf report_ids: {} (at most {})", report_ids.len(), MAX_REPORT_IDS));
+    }
+    report_ids.iter().map(|report_id| match u8::try_from(*report_id) {
+        // 0 means the device doesn't number its reports, byte 0 is data then
+        Ok(report_id) if report_id != 0 => Ok(report_id),
+        _ => Err(format!("Invalid bpf report id {:#x}: report ids are 0x01 to 0xff", report_id)),
+    }).collect()
+}
+
+pub fn parse_log_ignore(log_ignore: &[u32]) -> Result<Vec<u8>, String> {
+    if log_ignore.len() > MAX_LOG_IGNORE {
+        return Err(format!("Too many bpf log_ignore scancodes: {} (at most {})", log_ignore.len(), MAX_LOG_IGNORE));
+    }
+    log_ignore.iter().map(|scancode| u8::try_from(*scancode)
+        .map_err(|_| format!("Invalid bpf log_ignore scancode {:#x}: scancodes are 0x00 to 0xff", scancode))
+    ).collect()
+}
+
+// scancode -> window in ns, as written to debounce_map
+pub fn parse_debounce(debounce: &[Debounce]) -> Result<Vec<(u8, u64)>, String> {
+    let mut seen = BTreeMap::new();
+    for entry in debounce {
+        if entry.scancode == 0 || entry.scancode > 0xff {
+            return Err(format!("Invalid bpf debounce scancode {:#x}: scancodes are 0x01 to 0xff", entry.scancode));
+        }
+        if entry.ms == 0 || entry.ms > MAX_DEBOUNCE_MS {
+            return Err(format!("Invalid bpf debounce window {}ms for {:#04x}: must be 1 to {}ms", entry.ms, entry.scancode, MAX_DEBOUNCE_MS));
+        }
+        if seen.insert(entry.scancode as u8, entry.ms as u64 * 1_000_000).is_some() {
+            return Err(format!("Scancode {:#04x} is debounced twice", entry.scancode));
+        }
+    }
+    Ok(seen.into_iter().collect())
+}
+
+// checks the rules fit struct remap_rule and can match something
+pub fn parse_rules(rules: &[RemapRule]) -> Result<Vec<Rule>, String> {
+    if rules.len() > MAX_RULES {
+        return Err(format!("Too many bpf rules: {} (at most {})", rules.len(), MAX_RULES));
+    }
+    let byte = |name: &str, value: u32| -> Result<u8, String> {
+        u8::try_from(value).map_err(|_| format!("Invalid bpf rule {} {:#x}: must be 0x00 to 0xff", name, value))
+    };
+    rules.iter().map(|rule| {
+        let parsed = Rule {
+            report_id: byte("report_id", rule.report_id)?,
+            offset: byte("offset", rule.offset)?,
+            value: byte("value", rule.value)?,
+            mask: byte("mask", rule.mask.unwrap_or(0xff))?,
+            to: byte("to", rule.to)?,
+        };
+        // byte 0 is the report id itself
+        if parsed.offset == 0 || parsed.offset as usize >= RULE_REPORT_BYTES {
+            return Err(format!("Invalid bpf rule offset {}: must be 1 to {}", parsed.offset, RULE_REPORT_BYTES - 1));
+        }
+        if parsed.mask == 0 {
+            return Err("Invalid bpf rule mask 0: it would match every report".to_string());
+        }
+        if parsed.value & !parsed.mask != 0 {
+            return Err(format!("Bpf rule value {:#04x} has bits outside mask {:#04x}, it can never match", parsed.value, parsed.mask));
+        }
+        Ok(parsed)
+    }).collect()
+}
+
+// logs an entry the same way whether it came from the ring buffer or the model
+pub fn log_entry(entry: &LogEntry) {
+    if let Some(reason) = entry.reason {
+        debug!(
+            target: "bpf",
+            scancode = %format!("{:#04x}", entry.original),
+            reason = reason.name(),
+            "Dropped press"
+        );
+    } else if let Some(rule) = entry.rule {
+        debug!(
+            target: "bpf",
+            rule,
+            report_id = %format!("{:#04x}", entry.report_id),
+            offset = entry.offset,
+            value = %format!("{:#04x}", entry.original),
+            rewritten_to = %format!("{:#04x}", entry.new),
+            "Rule matched"
+        );
+    } else if entry.remapped {
+        debug!(
+            target: "bpf",
+            scancode = %format!("{:#04x}", entry.original),
+            remapped_to = %format!("{:#04x}", entry.new),
+            "Remapped scancode"
+        );
+    } else {
+        debug!(target: "bpf", scancode = %format!("{:#04x}", entry.original), "Unmapped scancode");
+    }
+}
+
+// hex bytes, optionally separated by spaces, colons or commas, e.g. "5a 4e 00 00 00 00"
+pub fn parse_report(value: &str) -> Result<Vec<u8>, String> {
+    let digits: String = value.chars()
+        .filter(|c| !c.is_whitespace() && *c != ':' && *c != ',')
+        .collect();
+    let digits = digits.trim_start_matches("0x");
+    if digits.is_empty() || !digits.len().is_multiple_of(2) {
+        return Err(format!("Invalid report {}: expected an even number of hex digits", value));
+    }
+    (0..digits.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).map_err(|e| format!("Invalid report {}: {}", value, e)))
+        .collect()
+}
+
+// simulate-remap <report> [config_path]: show what the bpf program would make of a report
+pub fn simulate_remap(args: &[String], default_config_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let report = args.first().ok_or("simulate-remap needs a report, e.g. \"5a 4e 00 00 00 00\"")?;
+    let config_path = args.get(1).map(|path| path.as_str()).unwrap_or(default_config_path);
+    let config = get_config(config_path);
+    let mut table = RemapTable::new(&config.bpf)?;
+
+    let original = parse_report(report)?;
+    let mut rewritten = original.clone();
+    let entry = table.apply(&mut rewritten, 0);
+
+    println!("report:    {:02x?}", original);
+    println!("rewritten: {:02x?}", rewritten);
+    match entry {
+        Some(LogEntry { rule: Some(rule), report_id, offset, original, new, .. }) => println!(
+            "log entry: rule {} matched report {:#04x} byte {}, {:#04x} rewritten to {:#04x}",
+            rule, report_id, offset, original, new
+        ),
+        Some(entry) if entry.remapped => println!("log entry: scancode {:#04x} remapped to {:#04x}", entry.original, entry.new),
+        Some(entry) => println!("log entry: scancode {:#04x} not remapped", entry.original),
+        None if original.is_empty() || !table.is_hotkey_report(original[0]) => {
+            println!("log entry: none, no rule matched and report id {:#04x} is not in bpf.report_ids", original.first().unwrap_or(&0))
+        }
+        None if original.len() < MIN_REPORT_LEN => println!("log entry: none, no rule matched and hotkey reports are at least {} bytes", MIN_REPORT_LEN),
+        None if original[1] == 0 => println!("log entry: none, key releases are passed through"),
+        None => println!("log entry: none, scancode {:#04x} is in bpf.log_ignore", original[1]),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apkt_config::{Debounce, RemapRule};
+    use crate::test_util::TempDir;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    const MS: u64 = 1_000_000;
+
+    fn base_config() -> BpfConfig {
+        BpfConfig {
+            enabled: true,
+            backend: "auto".to_string(),
+            map_type: "hash".to_string(),
+            map_size: None,
+            remaps: vec![Remap { from: 0x4e, to: 0x4f }],
+            rules: Vec::new(),
+            report_ids: vec![0x5a],
+            log_ignore: Vec::new(),
+            debounce: Vec::new(),
+        }
+    }
+
+    fn rule(report_id: u32, offset: u32, value: u32, mask: Option<u32>, to: u32) -> RemapRule {
+        RemapRule { report_id, offset, value, mask, to }
+    }
+
+    fn scancode_entry(original: u8, remapped: bool, new: u8) -> LogEntry {
+        LogEntry { original, remapped, new, report_id: 0x5a, offset: 1, rule: None, reason: None }
+    }
+
+    #[test]
+    fn remaps_configured_scancodes() {
+        let mut table = RemapTable::new(&base_config()).unwrap();
+        let mut report = [0x5a, 0x4e, 0, 0, 0, 0];
+        assert_eq!(table.apply(&mut report, MS), Some(scancode_entry(0x4e, true, 0x4f)));
+        assert_eq!(report, [0x5a, 0x4f, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn passes_other_scancodes_and_releases() {
+        let mut table = RemapTable::new(&base_config()).unwrap();
+        let mut report = [0x5a, 0x10, 0, 0, 0, 0];
+        assert_eq!(table.apply(&mut report, MS), Some(scancode_entry(0x10, false, 0)));
+        assert_eq!(report, [0x5a, 0x10, 0, 0, 0, 0]);
+        let mut release = [0x5a, 0, 0, 0, 0, 0];
+        assert_eq!(table.apply(&mut release, MS), None);
+    }
+
+    #[test]
+    fn remaps_off_without_entries() {
+        let mut table = RemapTable::new(&BpfConfig { remaps: Vec::new(), ..base_config() }).unwrap();
+        let mut report = [0x5a, 0x4e, 0, 0, 0, 0];
+        assert_eq!(table.apply(&mut report, MS), Some(scancode_entry(0x4e, false, 0)));
+        assert_eq!(report, [0x5a, 0x4e, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn first_matching_rule_skips_the_remaps() {
+        let config = BpfConfig {
+            rules: vec![rule(0x5a, 1, 0x4e, None, 0x50), rule(0x5a, 1, 0x4e, None, 0x51)],
+            ..base_config()
+        };
+        let mut table = RemapTable::new(&config).unwrap();
+        let mut report = [0x5a, 0x4e, 0, 0, 0, 0];
+        let entry = table.apply(&mut report, MS).unwrap();
+        assert_eq!(report, [0x5a, 0x50, 0, 0, 0, 0]);
+        assert_eq!((entry.rule, entry.original, entry.new, entry.offset), (Some(0), 0x4e, 0x50, 1));
+    }
+
+    #[test]
+    fn rules_replace_only_masked_bits() {
+        let config = BpfConfig { rules: vec![rule(0x5d, 3, 0x10, Some(0xf0), 0x20)], ..base_config() };
+        let mut table = RemapTable::new(&config).unwrap();
+        let mut report = [0x5d, 0, 0, 0x1f, 0, 0, 0, 0];
+        let entry = table.apply(&mut report, MS).unwrap();
+        assert_eq!(report, [0x5d, 0, 0, 0x2f, 0, 0, 0, 0]);
+        assert_eq!((entry.report_id, entry.rule, entry.original, entry.new), (0x5d, Some(0), 0x1f, 0x2f));
+        let mut other = [0x5d, 0, 0, 0x2f, 0, 0, 0, 0];
+        assert_eq!(table.apply(&mut other, MS), None);
+    }
+
+    #[test]
+    fn only_configured_report_ids_are_remapped() {
+        let mut table = RemapTable::new(&base_config()).unwrap();
+        let mut report = [0x5b, 0x4e, 0, 0, 0, 0];
+        assert_eq!(table.apply(&mut report, MS), None);
+        assert_eq!(report, [0x5b, 0x4e, 0, 0, 0, 0]);
+
+        let mut table = RemapTable::new(&BpfConfig { report_ids: vec![0x5a, 0x5b], ..base_config() }).unwrap();
+        let entry = table.apply(&mut report, MS).unwrap();
+        assert_eq!((entry.report_id, entry.new), (0x5b, 0x4f));
+        assert_eq!(report, [0x5b, 0x4f, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn short_reports_are_not_remapped() {
+        let mut table = RemapTable::new(&base_config()).unwrap();
+        let mut report = [0x5a, 0x4e, 0, 0, 0];
+        assert_eq!(table.apply(&mut report, MS), None);
+        assert_eq!(report, [0x5a, 0x4e, 0, 0, 0]);
+        assert_eq!(table.apply(&mut [], MS), None);
+    }
+
+    #[test]
+    fn debounces_presses_within_the_window() {
+        let config = BpfConfig { debounce: vec![Debounce { scancode: 0x4e, ms: 50 }], ..base_config() };
+        let mut table = RemapTable::new(&config).unwrap();
+        let mut press = |now_ns: u64| {
+            let mut report = [0x5a, 0x4e, 0, 0, 0, 0];
+            (table.apply(&mut report, now_ns).and_then(|entry| entry.reason), report[1])
+        };
+        assert_eq!(press(1000 * MS), (None, 0x4f));
+        // dropped before the remap
+        assert_eq!(press(1030 * MS), (Some(DropReason::Debounce), 0x4e));
+        // the window runs from the last press let through
+        assert_eq!(press(1049 * MS), (Some(DropReason::Debounce), 0x4e));
+        assert_eq!(press(1050 * MS), (None, 0x4f));
+        // other scancodes aren't debounced
+        let mut report = [0x5a, 0x10, 0, 0, 0, 0];
+        assert_eq!(table.apply(&mut report, 1051 * MS).and_then(|entry| entry.reason), None);
+    }
+
+    #[test]
+    fn log_ignore_applies_after_the_remap() {
+        let config = BpfConfig {
+            log_ignore: vec![0x4e],
+            debounce: vec![Debounce { scancode: 0x4e, ms: 50 }],
+            ..base_config()
+        };
+        let mut table = RemapTable::new(&config).unwrap();
+        let mut report = [0x5a, 0x4e, 0, 0, 0, 0];
+        assert_eq!(table.apply(&mut report, 1000 * MS), None);
+        assert_eq!(report, [0x5a, 0x4f, 0, 0, 0, 0]);
+        // dropped presses are logged anyway
+        let mut report = [0x5a, 0x4e, 0, 0, 0, 0];
+        assert_eq!(table.apply(&mut report, 1010 * MS).and_then(|entry| entry.reason), Some(DropReason::Debounce));
+
+        // matched against the scancode before the remap
+        let mut table = RemapTable::new(&BpfConfig { log_ignore: vec![0x4f], ..base_config() }).unwrap();
+        let mut report = [0x5a, 0x4e, 0, 0, 0, 0];
+        assert_eq!(table.apply(&mut report, MS), Some(scancode_entry(0x4e, true, 0x4f)));
+    }
+
+    #[test]
+    fn parses_reports() {
+        let expected = vec![0x5a, 0x4e, 0x00];
+        for value in ["5a 4e 00", "5a:4e:00", "5a,4e,00", "0x5a4e00", "5A4E00"] {
+            assert_eq!(parse_report(value), Ok(expected.clone()), "{}", value);
+        }
+        for value in ["", "0x", "5a4", "5a 4", "zz", "5a 4g"] {
+            assert!(parse_report(value).is_err(), "{}", value);
+        }
+    }
+
+    #[test]
+    fn rejects_values_the_maps_cant_hold() {
+        let invalid = [
+            BpfConfig { remaps: vec![Remap { from: 0x4e, to: 0x100 }], ..base_config() },
+            BpfConfig { remaps: vec![Remap { from: 0x100, to: 0x4e }], ..base_config() },
+            BpfConfig { rules: vec![rule(0x5a, 1, 0x4e, None, 0x100)], ..base_config() },
+            BpfConfig { rules: vec![rule(0x5a, RULE_REPORT_BYTES as u32, 0x4e, None, 0x4f)], ..base_config() },
+            BpfConfig { rules: vec![rule(0x5a, 0, 0x5a, None, 0x5b)], ..base_config() },
+            BpfConfig { rules: vec![rule(0x5a, 1, 0x4e, Some(0x0f), 0x4f)], ..base_config() },
+        ];
+        for config in invalid {
+            assert!(RemapTable::new(&config).is_err(), "accepted {:?}", config);
+        }
+        let valid = BpfConfig { rules: vec![rule(0x5a, RULE_REPORT_BYTES as u32 - 1, 0x4e, None, 0xff)], ..base_config() };
+        assert!(RemapTable::new(&valid).is_ok());
+    }
+
+    // xorshift64, so failures reproduce
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+
+        fn byte(&mut self) -> u8 {
+            self.next() as u8
+        }
+
+        fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+            items[self.below(items.len() as u64) as usize]
+        }
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    fn random_config(random: &mut Random) -> BpfConfig {
+        let mut config = BpfConfig {
+            map_type: random.pick(&["hash", "array"]).to_string(),
+            remaps: Vec::new(),
+            report_ids: vec![0x5a],
+            ..base_config()
+        };
+        if random.below(3) == 0 {
+            config.report_ids.push(1 + random.below(255) as u32);
+        }
+        for _ in 0..random.below(5) {
+            let from = 1 + random.below(255) as u32;
+            if !config.remaps.iter().any(|remap| remap.from == from) {
+                config.remaps.push(Remap { from, to: random.below(256) as u32 });
+            }
+        }
+        let scancodes: Vec<u32> = config.remaps.iter().map(|remap| remap.from).chain([0x4e, 0x7e]).collect();
+        for _ in 0..random.below(4) {
+            let configured = random.pick(&config.report_ids);
+            let report_id = random.pick(&[0x5a, 0x5b, configured]);
+            let mask = 1 + random.below(255) as u32;
+            let offset = 1 + random.below(RULE_REPORT_BYTES as u64 - 1) as u32;
+            let value = if offset == 1 { random.pick(&scancodes) } else { random.byte() as u32 };
+            let value = value & mask;
+            config.rules.push(rule(report_id, offset, value, Some(mask), random.byte() as u32));
+        }
+        for _ in 0..random.below(3) {
+            config.log_ignore.push(random.pick(&scancodes));
+        }
+        for _ in 0..random.below(3) {
+            let scancode = random.pick(&scancodes);
+            if !config.debounce.iter().any(|debounce| debounce.scancode == scancode) {
+                config.debounce.push(Debounce { scancode, ms: 1 + random.below(40) as u32 });
+            }
+        }
+        config
+    }
+
+    // the map contents bpf_loader would write for the config
+    fn map_commands(config: &BpfConfig) -> Vec<String> {
+        let mut commands = vec!["reset".to_string()];
+        let remap_map = if config.map_type == "array" { "remap_array" } else { "remap_map" };
+        for remap in &config.remaps {
+            commands.push(format!("set {} {} {}", remap_map, remap.from, hex(&(remap.to | 0x100).to_le_bytes())));
+        }
+        for (index, rule) in parse_rules(&config.rules).unwrap().iter().enumerate() {
+            commands.push(format!("set rule_map {} {}", index, hex(&rule.to_bytes())));
+            commands.push(format!("set rule_report_id_map {} 01", rule.report_id));
+        }
+        for report_id in parse_report_ids(&config.report_ids).unwrap() {
+            commands.push(format!("set report_id_map {} 01", report_id));
+        }
+        for scancode in parse_log_ignore(&config.log_ignore).unwrap() {
+            commands.push(format!("set log_ignore_map {} 01", scancode));
+        }
+        for (scancode, window) in parse_debounce(&config.debounce).unwrap() {
+            commands.push(format!("set debounce_map {} {}", scancode, hex(&window.to_le_bytes())));
+        }
+        commands
+    }
+
+    // what the harness prints for an event, from the model
+    fn model_output(entry: Option<LogEntry>, report: &[u8; RULE_REPORT_BYTES]) -> String {
+        let ret = if entry.is_some_and(|entry| entry.reason.is_some()) { -1 } else { 0 };
+        let mut output = format!("{} {}", ret, hex(report));
+        match entry {
+            Some(entry) => output.push_str(&format!(
+                " 1 {} {} {} {} {} {} {}",
+                entry.original,
+                entry.remapped as i32,
+                entry.new,
+                entry.report_id,
+                entry.offset,
+                entry.rule.map(|rule| rule as i32).unwrap_or(-1),
+                entry.reason.map(|_| 1).unwrap_or(0)
+            )),
+            None => output.push_str(" 0"),
+        }
+        output
+    }
+
+    // compiles bpf/hid_modify.bpf.c as a normal program with the shims in bpf/model_check and feeds
+    // it and the model the same random configs and reports
+    #[test]
+    fn model_matches_the_bpf_program() {
+        if Command::new("cc").arg("--version").output().is_err() {
+            eprintln!("cc not found, skipping the bpf program comparison");
+            return;
+        }
+        let bpf_dir = format!("{}/src/bpf", env!("CARGO_MANIFEST_DIR"));
+        let build = TempDir::new("bpf-model");
+        let harness = format!("{}/harness", build.path());
+        let compiled = Command::new("cc")
+            .args(["-w", "-O1", "-I", &format!("{}/model_check", bpf_dir), "-I", &bpf_dir])
+            .arg(format!("{}/model_check/prog.c", bpf_dir))
+            .arg(format!("{}/model_check/main.c", bpf_dir))
+            .args(["-o", &harness])
+            .output()
+            .expect("Failed to run cc");
+        assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+
+        let mut random = Random(0x5a4e_5a4e_5a4e_5a4e);
+        let mut commands: Vec<String> = Vec::new();
+        let mut expected: Vec<(String, String)> = Vec::new();
+        for case in 0..300 {
+            let config = random_config(&mut random);
+            let mut table = RemapTable::new(&config).unwrap();
+            commands.extend(map_commands(&config));
+
+            let report_ids: Vec<u8> = config.report_ids.iter().chain(config.rules.iter().map(|rule| &rule.report_id))
+                .map(|report_id| *report_id as u8)
+                .collect();
+            let mut scancodes: Vec<u8> = config.remaps.iter().map(|remap| remap.from as u8).collect();
+            scancodes.extend(config.debounce.iter().map(|debounce| debounce.scancode as u8));
+            scancodes.push(0);
+            let mut now_ns = 1000 * MS;
+            for _ in 0..24 {
+                now_ns += random.below(40) * MS;
+                let mut report = [0u8; RULE_REPORT_BYTES];
+                report.iter_mut().for_each(|byte| *byte = random.byte());
+                if random.below(4) != 0 {
+                    report[0] = random.pick(&report_ids);
+                }
+                if random.below(4) != 0 {
+                    report[1] = random.pick(&scancodes);
+                }
+                if !config.rules.is_empty() && random.below(2) == 0 {
+                    let rule = &config.rules[random.below(config.rules.len() as u64) as usize];
+                    let mask = rule.mask.unwrap_or(0xff) as u8;
+                    report[rule.offset as usize] = rule.value as u8 | (random.byte() & !mask);
+                }
+                let any_size = 1 + random.below(RULE_REPORT_BYTES as u64) as usize;
+                let size = random.pick(&[RULE_REPORT_BYTES, RULE_REPORT_BYTES, MIN_REPORT_LEN, any_size]);
+
+                let command = format!("event {} {} {}", now_ns, size, hex(&report));
+                let entry = table.apply(&mut report[..size], now_ns);
+                expected.push((format!("case {}: {:?}\n{}", case, config, command), model_output(entry, &report)));
+                commands.push(command);
+            }
+        }
+
+        let mut child = Command::new(&harness)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to start the harness");
+        let mut stdin = child.stdin.take().unwrap();
+        let input = commands.join("\n") + "\n";
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child.wait_with_output().expect("Failed to run the harness");
+        writer.join().unwrap().expect("Failed to write to the harness");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let lines: Vec<&str> = stdout.lines().collect();
+        assert_eq!(lines.len(), expected.len());
+        for (line, (context, model)) in lines.iter().zip(&expected) {
+            assert_eq!(line, model, "bpf program and model disagree on {}", context);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+// last time each watched loop made progress, in ms since START.  0 means never, IDLE not watched
+static EVENT_LOOP_HEARTBEAT: AtomicU64 = AtomicU64::new(0);
+static BPF_HEARTBEAT: AtomicU64 = AtomicU64::new(0);
+static IDLE: u64 = u64::MAX;
+static START: OnceLock<Instant> = OnceLock::new();
+
+pub enum Heartbeat {
+    EventLoop,
+    Bpf,
+}
+
+// send a state string (READY=1, STATUS=..., WATCHDOG=1) to systemd.  does nothing when not
+// started by systemd with Type=notify
+pub fn notify(state: &str) {
+    let Ok(socket_path) = std::env::var("NOTIFY_SOCKET") else {
+        return;
+    };
+    let addr = if let Some(name) = socket_path.strip_prefix('@') {
+        SocketAddr::from_abstract_name(name.as_bytes())
+    } else {
+        SocketAddr::from_pathname(&socket_path)
+    };
+    let res = addr.and_then(|addr| {
+        let socket = UnixDatagram::unbound()?;
+        socket.send_to_addr(state.as_bytes(), &addr)
+    });
+    if let Err(e) = res {
+        warn!("Failed to notify systemd ({}): {}", state, e);
+    }
+}
+
+pub fn heartbeat(source: Heartbeat) {
+    let now = START.get_or_init(Instant::now).elapsed().as_millis() as u64;
+    match source {
+        Heartbeat::EventLoop => EVENT_LOOP_HEARTBEAT.store(now.max(1), Ordering::Relaxed),
+        Heartbeat::Bpf => BPF_HEARTBEAT.store(now.max(1), Ordering::Relaxed),
+    }
+}
+
+// a loop waiting for its device to come back isn't stalled, it's ignored until its next heartbeat
+pub fn heartbeat_idle(source: Heartbeat) {
+    match source {
+        Heartbeat::EventLoop => EVENT_LOOP_HEARTBEAT.store(IDLE, Ordering::Relaxed),
+        Heartbeat::Bpf => BPF_HEARTBEAT.store(IDLE, Ordering::Relaxed),
+    }
+}
+
+// WatchdogSec= from the unit, if the watchdog is meant for this process
+fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    let usec = std::env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+    Some(Duration::from_micros(usec))
+}
+
+// ping the watchdog at half its interval, but only while the event loop (and the bpf ring buffer
+// thread, if bpf is used) have made progress within the interval.  a hung loop stops the pings
+// and systemd restarts the service
+pub fn start_watchdog(watch_bpf: bool) {
+    let Some(interval) = watchdog_interval() else {
+        return;
+    };
+    let start = *START.get_or_init(Instant::now);
+    info!("systemd watchdog enabled, interval {:?}", interval);
+
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval / 2).await;
+            let now = start.elapsed().as_millis() as u64;
+            let limit = interval.as_millis() as u64;
+            let alive = |heartbeat: &AtomicU64| {
+                let last = heartbeat.load(Ordering::Relaxed);
+                last == IDLE || (last != 0 && now.saturating_sub(last) < limit)
+            };
+            if !alive(&EVENT_LOOP_HEARTBEAT) {
+                error!("Event loop stalled, withholding watchdog ping");
+                continue;
+            }
+            if watch_bpf && !alive(&BPF_HEARTBEAT) {
+                error!("BPF ring buffer thread stalled, withholding watchdog ping");
+                continue;
+            }
+            notify("WATCHDOG=1");
+        }
+    });
+}
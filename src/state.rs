@@ -1,19 +1,380 @@
-static FILE_ROOT: &str = "/var/lib/asus-px-kb-tool";
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Mutex, OnceLock};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, info, warn};
+
+static DEFAULT_FILE_ROOT: &str = "/var/lib/asus-px-kb-tool";
+static FILE_ROOT: OnceLock<String> = OnceLock::new();
+static STATE_VERSION: u32 = 1;
+// the state file and the temp file it is written through, handed to the daemon user on the drop
+pub static STATE_FILES: [&str; 2] = ["state.toml", "state.toml.tmp"];
+
+// state.toml replaced these single value files
+static LEGACY_FN_LOCK_FILE: &str = "state";
+static LEGACY_BACKLIGHT_FILE: &str = "backlight";
+static LEGACY_TABLET_BACKLIGHT_FILE: &str = "tablet_backlight";
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct StateDocument {
+    pub version: u32,
+    pub fn_lock: Option<bool>,
+    // level of the first managed led, used for leds without their own entry
+    pub backlight: Option<u32>,
+    pub tablet_backlight: Option<u32>,
+    // keyed by led path
+    #[serde(default)]
+    pub devices: BTreeMap<String, DeviceState>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DeviceState {
+    pub backlight: Option<u32>,
+    pub tablet_backlight: Option<u32>,
+}
+
+static STATE: Mutex<Option<StateDocument>> = Mutex::new(None);
+// set when the state file was written by a newer version, it is left alone then
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+static WRITER: OnceLock<Sender<StateWrite>> = OnceLock::new();
+
+enum StateWrite {
+    Write(StateDocument),
+    // answered once everything sent before it is on disk
+    Flush(Sender<()>),
+}
+
+// must be called before the state is first used
+pub fn set_state_dir(dir: &str) {
+    FILE_ROOT.set(dir.to_string()).expect("State directory already set");
+}
+
+pub fn state_dir() -> &'static str {
+    FILE_ROOT.get_or_init(|| DEFAULT_FILE_ROOT.to_string())
+}
+
+fn state_path(dir: &str) -> String {
+    format!("{}/{}", dir, STATE_FILES[0])
+}
+
+// run f against the cached state document, loading it on first use
+fn with_state<T>(f: impl FnOnce(&mut StateDocument) -> T) -> T {
+    let mut state = STATE.lock().expect("Failed to lock state");
+    let document = state.get_or_insert_with(|| read_state(state_dir()));
+    f(document)
+}
+
+// modify the state document and queue it for writing.  the write and its fsyncs happen on the
+// writer thread, so the async tasks saving state never block on the disk
+fn update_state(f: impl FnOnce(&mut StateDocument)) {
+    let document = with_state(|document| {
+        f(document);
+        document.clone()
+    });
+    if READ_ONLY.load(Ordering::Relaxed) {
+        debug!("Not saving state, {} is from a newer version", state_path(state_dir()));
+        return;
+    }
+    if writer().send(StateWrite::Write(document)).is_err() {
+        error!("Unable to save state to {}: writer thread exited", state_path(state_dir()));
+    }
+}
+
+// wait until every queued state change is written, e.g. before exiting
+pub fn flush_state() {
+    let (done, wait) = channel();
+    if writer().send(StateWrite::Flush(done)).is_ok() {
+        let _ = wait.recv();
+    }
+}
+
+fn writer() -> &'static Sender<StateWrite> {
+    WRITER.get_or_init(|| {
+        let (sender, receiver) = channel();
+        std::thread::spawn(move || run_writer(receiver, |document| {
+            if let Err(e) = write_state(state_dir(), document) {
+                error!("Unable to save state to {}: {}", state_path(state_dir()), e);
+            }
+        }));
+        sender
+    })
+}
+
+fn run_writer(receiver: Receiver<StateWrite>, mut write: impl FnMut(&StateDocument)) {
+    while let Ok(request) = receiver.recv() {
+        // only the newest document of a burst needs writing
+        let mut latest = None;
+        let mut flushes = Vec::new();
+        for request in std::iter::once(request).chain(receiver.try_iter()) {
+            match request {
+                StateWrite::Write(document) => latest = Some(document),
+                StateWrite::Flush(done) => flushes.push(done),
+            }
+        }
+        if let Some(document) = latest {
+            write(&document);
+        }
+        for done in flushes {
+            let _ = done.send(());
+        }
+    }
+}
+
+fn read_state(dir: &str) -> StateDocument {
+    let path = state_path(dir);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return migrate_legacy_state(dir),
+        Err(e) => {
+            warn!("Unable to read state file {}, using defaults: {}", path, e);
+            return StateDocument { version: STATE_VERSION, ..Default::default() };
+        }
+    };
+
+    match toml::from_str::<StateDocument>(&contents) {
+        Ok(mut document) if document.version <= STATE_VERSION => {
+            // older documents are written back in the current format
+            document.version = STATE_VERSION;
+            document
+        }
+        Ok(document) => {
+            warn!(
+                "State file {} has version {}, newer than the supported version {}. Using defaults and leaving it untouched",
+                path, document.version, STATE_VERSION
+            );
+            READ_ONLY.store(true, Ordering::Relaxed);
+            StateDocument { version: STATE_VERSION, ..Default::default() }
+        }
+        Err(e) => {
+            // keep the broken file around for bug reports, it would be overwritten otherwise
+            let corrupt_path = format!("{}.corrupt", path);
+            error!("State file {} is corrupt, moving it to {} and using defaults: {}", path, corrupt_path, e);
+            if let Err(e) = std::fs::rename(&path, &corrupt_path) {
+                error!("Unable to move corrupt state file: {}", e);
+            }
+            StateDocument { version: STATE_VERSION, ..Default::default() }
+        }
+    }
+}
+
+// build the state document from the old single value files, then remove them
+fn migrate_legacy_state(dir: &str) -> StateDocument {
+    let mut document = StateDocument { version: STATE_VERSION, ..Default::default() };
+    let read_legacy = |name: &str| -> Option<String> {
+        std::fs::read_to_string(format!("{}/{}", dir, name)).ok()
+            .map(|contents| contents.trim().to_string())
+    };
+
+    let mut migrated = false;
+    if let Some(contents) = read_legacy(LEGACY_FN_LOCK_FILE) {
+        match contents.as_str() {
+            "1" => document.fn_lock = Some(true),
+            "0" => document.fn_lock = Some(false),
+            _ => warn!("Ignoring unrecognized legacy fn-lock state: {:?}", contents),
+        }
+        migrated = true;
+    }
+    if let Some(contents) = read_legacy(LEGACY_BACKLIGHT_FILE) {
+        document.backlight = contents.parse::<u32>().ok();
+        migrated = true;
+    }
+    if let Some(contents) = read_legacy(LEGACY_TABLET_BACKLIGHT_FILE) {
+        document.tablet_backlight = contents.parse::<u32>().ok();
+        migrated = true;
+    }
+
+    if migrated {
+        info!("Migrating legacy state files to {}", state_path(dir));
+        match write_state(dir, &document) {
+            Ok(_) => {
+                for name in [LEGACY_FN_LOCK_FILE, LEGACY_BACKLIGHT_FILE, LEGACY_TABLET_BACKLIGHT_FILE] {
+                    let _ = std::fs::remove_file(format!("{}/{}", dir, name));
+                }
+            }
+            Err(e) => error!("Unable to write migrated state: {}", e),
+        }
+    }
+    document // return value
+}
+
+// write to a temp file, fsync it and rename it over the old state so a power loss can't leave
+// a truncated file behind
+fn write_state(dir: &str, document: &StateDocument) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let contents = toml::to_string(document)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+    let path = state_path(dir);
+    let tmp_path = format!("{}/{}", dir, STATE_FILES[1]);
+    let mut file = std::fs::File::create(&tmp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, &path)?;
+    // make the rename itself durable
+    std::fs::File::open(dir)?.sync_all()
+}
 
 pub fn save_state(state: bool) {
-    let filename = format!("{}/state", FILE_ROOT);
-    // save bool as either 1 or 0 in the file
-    // create the directory if it doesn't exist
-    std::fs::create_dir_all(FILE_ROOT)
-        .expect("Unable to create directory");
-    std::fs::write(filename, if state { "1" } else { "0" })
-        .expect("Unable to write file");
+    update_state(|document| document.fn_lock = Some(state));
 }
 
 pub fn load_state() -> bool {
-    // load the state from file.  if the file is invalid or missing, use default false
-    let filename = format!("{}/state", FILE_ROOT);
-    let contents = std::fs::read_to_string(filename)
-        .unwrap_or_else(|_| "0".to_string());
-    contents.trim() == "1"
-}
\ No newline at end of file
+    // defaults to false if nothing was saved yet
+    with_state(|document| document.fn_lock.unwrap_or(false))
+}
+
+pub fn save_backlight_state(levels: &[(String, u32)]) {
+    // levels picked with the cycle key
+    update_state(|document| {
+        document.backlight = levels.first().map(|(_, level)| *level);
+        for (led_path, level) in levels {
+            document.devices.entry(led_path.clone()).or_default().backlight = Some(*level);
+        }
+    });
+}
+
+pub fn load_backlight_state(led_path: &str) -> Option<u32> {
+    // None if nothing was saved yet
+    with_state(|document| {
+        document.devices.get(led_path)
+            .and_then(|device| device.backlight)
+            .or(document.backlight)
+    })
+}
+
+pub fn save_tablet_backlight_state(levels: &[(String, u32)]) {
+    // brightness to restore when leaving tablet mode.  empty when there is nothing to restore
+    update_state(|document| {
+        document.tablet_backlight = levels.first().map(|(_, level)| *level);
+        for device in document.devices.values_mut() {
+            device.tablet_backlight = None;
+        }
+        for (led_path, level) in levels {
+            document.devices.entry(led_path.clone()).or_default().tablet_backlight = Some(*level);
+        }
+    });
+}
+
+pub fn load_tablet_backlight_state(led_path: &str) -> Option<u32> {
+    with_state(|document| {
+        document.devices.get(led_path)
+            .and_then(|device| device.tablet_backlight)
+            .or(document.tablet_backlight)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn exists(dir: &TempDir, name: &str) -> bool {
+        std::path::Path::new(&format!("{}/{}", dir.path(), name)).exists()
+    }
+
+    #[test]
+    fn migrates_and_removes_legacy_files() {
+        let dir = TempDir::new("state-legacy");
+        dir.write(LEGACY_FN_LOCK_FILE, "1\n");
+        dir.write(LEGACY_BACKLIGHT_FILE, "2\n");
+        dir.write(LEGACY_TABLET_BACKLIGHT_FILE, "3\n");
+
+        let document = read_state(dir.path());
+        assert_eq!(document.version, STATE_VERSION);
+        assert_eq!((document.fn_lock, document.backlight, document.tablet_backlight), (Some(true), Some(2), Some(3)));
+        for name in [LEGACY_FN_LOCK_FILE, LEGACY_BACKLIGHT_FILE, LEGACY_TABLET_BACKLIGHT_FILE] {
+            assert!(!exists(&dir, name), "{} left behind", name);
+        }
+        let written = read_state(dir.path());
+        assert_eq!((written.fn_lock, written.backlight, written.tablet_backlight), (Some(true), Some(2), Some(3)));
+    }
+
+    #[test]
+    fn starts_empty_without_any_files() {
+        let dir = TempDir::new("state-empty");
+        let document = read_state(dir.path());
+        assert_eq!(document.version, STATE_VERSION);
+        assert_eq!(document.fn_lock, None);
+        assert!(!exists(&dir, STATE_FILES[0]));
+    }
+
+    #[test]
+    fn moves_corrupt_files_aside() {
+        let dir = TempDir::new("state-corrupt");
+        dir.write(STATE_FILES[0], "version = [\n");
+        let document = read_state(dir.path());
+        assert_eq!((document.version, document.fn_lock), (STATE_VERSION, None));
+        assert!(!exists(&dir, STATE_FILES[0]));
+        let corrupt = std::fs::read_to_string(format!("{}/{}.corrupt", dir.path(), STATE_FILES[0])).unwrap();
+        assert_eq!(corrupt, "version = [\n");
+    }
+
+    #[test]
+    fn leaves_newer_files_untouched() {
+        let dir = TempDir::new("state-newer");
+        let newer = format!("version = {}\nfn_lock = true\nsomething_new = 1\n", STATE_VERSION + 1);
+        let path = dir.write(STATE_FILES[0], &newer);
+        let document = read_state(dir.path());
+        assert_eq!((document.version, document.fn_lock), (STATE_VERSION, None));
+        assert!(READ_ONLY.load(Ordering::Relaxed));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), newer);
+    }
+
+    #[test]
+    fn upgrades_older_versions() {
+        let dir = TempDir::new("state-older");
+        dir.write(STATE_FILES[0], "version = 0\nfn_lock = false\n");
+        let document = read_state(dir.path());
+        assert_eq!((document.version, document.fn_lock), (STATE_VERSION, Some(false)));
+    }
+
+    #[test]
+    fn writes_through_the_temp_file() {
+        let dir = TempDir::new("state-write");
+        let document = StateDocument { version: STATE_VERSION, backlight: Some(1), ..Default::default() };
+        write_state(dir.path(), &document).unwrap();
+        assert!(!exists(&dir, STATE_FILES[1]));
+        assert_eq!(read_state(dir.path()).backlight, Some(1));
+    }
+
+    #[test]
+    fn writer_coalesces_queued_writes() {
+        let (sender, receiver) = channel();
+        let (done, wait) = channel();
+        for level in 1..=3 {
+            let document = StateDocument { backlight: Some(level), ..Default::default() };
+            sender.send(StateWrite::Write(document)).unwrap();
+        }
+        sender.send(StateWrite::Flush(done)).unwrap();
+        drop(sender);
+
+        let mut written = Vec::new();
+        run_writer(receiver, |document| written.push(document.backlight));
+        assert_eq!(written, vec![Some(3)]);
+        assert!(wait.try_recv().is_ok(), "flush not answered");
+    }
+
+    #[test]
+    fn writer_answers_flushes_after_the_write() {
+        let (sender, receiver) = channel();
+        let (done, wait) = channel();
+        let dir = TempDir::new("state-flush");
+        let root = dir.path().to_string();
+        let writer = std::thread::spawn(move || run_writer(receiver, |document| write_state(&root, document).unwrap()));
+
+        let document = StateDocument { version: STATE_VERSION, fn_lock: Some(true), ..Default::default() };
+        sender.send(StateWrite::Write(document)).unwrap();
+        sender.send(StateWrite::Flush(done)).unwrap();
+        wait.recv().unwrap();
+        assert_eq!(read_state(dir.path()).fn_lock, Some(true));
+        drop(sender);
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn flush_state_returns_with_nothing_queued() {
+        flush_state();
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+use tracing::warn;
+
+static DEFAULT_RUNTIME_ROOT: &str = "/run/asus-px-kb-tool";
+static RUNTIME_ROOT: OnceLock<String> = OnceLock::new();
+pub static STATUS_FILE: &str = "status";
+static STATUS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+// must be called before the first status is written
+pub fn set_runtime_dir(dir: &str) {
+    RUNTIME_ROOT.set(dir.to_string()).expect("Runtime directory already set");
+}
+
+pub fn runtime_dir() -> &'static str {
+    RUNTIME_ROOT.get_or_init(|| DEFAULT_RUNTIME_ROOT.to_string())
+}
+
+// record a status value and rewrite the status file read by the `status` command
+pub fn set_status(key: &str, value: &str) {
+    let mut status = STATUS.lock().expect("Failed to lock status");
+    status.insert(key.to_string(), value.to_string());
+
+    let mut contents = String::new();
+    for (key, value) in status.iter() {
+        contents.push_str(&format!("{}: {}\n", key, value));
+    }
+    let filename = format!("{}/{}", runtime_dir(), STATUS_FILE);
+    let res = std::fs::create_dir_all(runtime_dir())
+        .and_then(|_| std::fs::write(filename, contents));
+    if let Err(e) = res {
+        warn!("Unable to write status file: {}", e);
+    }
+}
+
+pub fn print_status() -> Result<(), Box<dyn std::error::Error>> {
+    print!("{}", read_status(runtime_dir())?);
+    Ok(())
+}
+
+fn read_status(dir: &str) -> Result<String, String> {
+    let filename = format!("{}/{}", dir, STATUS_FILE);
+    std::fs::read_to_string(&filename)
+        .map_err(|e| format!("Unable to read {} (is the service running?): {}", filename, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::resolve_directory;
+    use crate::test_util::TempDir;
+
+    static SYSTEMD_VARIABLE: &str = "ASUS_PX_KB_TOOL_TEST_RUNTIME_DIRECTORY";
+
+    #[test]
+    fn reads_the_status_file() {
+        let dir = TempDir::new("status-read");
+        dir.write(STATUS_FILE, "fnlock_backend: hid\nfnlock_state: on\n");
+        assert_eq!(read_status(dir.path()).unwrap(), "fnlock_backend: hid\nfnlock_state: on\n");
+    }
+
+    #[test]
+    fn fails_without_a_status_file() {
+        let dir = TempDir::new("status-missing");
+        assert!(read_status(dir.path()).is_err());
+    }
+
+    #[test]
+    fn prefers_the_flag_then_the_config_then_systemd() {
+        let dir = TempDir::new("status-order");
+        dir.write(&format!("flag/{}", STATUS_FILE), "source: flag\n");
+        dir.write(&format!("config/{}", STATUS_FILE), "source: config\n");
+        dir.write(&format!("systemd/{}", STATUS_FILE), "source: systemd\n");
+        let path = |name: &str| Some(format!("{}/{}", dir.path(), name));
+        // systemd may pass several directories, the first is used
+        std::env::set_var(SYSTEMD_VARIABLE, format!("{}:/nonexistent", path("systemd").unwrap()));
+
+        let status = |cli_dir, config_dir| read_status(&resolve_directory(&cli_dir, &config_dir, SYSTEMD_VARIABLE).unwrap());
+        assert_eq!(status(path("flag"), path("config")).unwrap(), "source: flag\n");
+        assert_eq!(status(None, path("config")).unwrap(), "source: config\n");
+        assert_eq!(status(None, None).unwrap(), "source: systemd\n");
+        std::env::remove_var(SYSTEMD_VARIABLE);
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::FileExt;
+use std::sync::Mutex;
+
+// sysfs attributes are kept open after first use, so they stay usable once privileges are dropped
+static OPEN_ATTRIBUTES: Mutex<BTreeMap<String, File>> = Mutex::new(BTreeMap::new());
+
+fn with_attribute<T>(path: &str, f: impl FnOnce(&File) -> std::io::Result<T>) -> std::io::Result<T> {
+    let mut open_attributes = OPEN_ATTRIBUTES.lock().expect("Failed to lock sysfs attributes");
+    if !open_attributes.contains_key(path) {
+        // read-only attributes (max_brightness) can't be opened for writing
+        let file = OpenOptions::new().read(true).write(true).open(path)
+            .or_else(|_| File::open(path))?;
+        open_attributes.insert(path.to_string(), file);
+    }
+    let result = f(&open_attributes[path]);
+    if result.is_err() {
+        // e.g. the device went away.  open it again next time in case it comes back
+        open_attributes.remove(path);
+    }
+    result
+}
+
+// open an attribute ahead of time without reading it
+pub fn open_attribute(path: &str) -> std::io::Result<()> {
+    with_attribute(path, |_| Ok(()))
+}
+
+pub fn read_attribute(path: &str) -> std::io::Result<String> {
+    with_attribute(path, |file| {
+        // sysfs regenerates the value on every read from offset 0
+        let mut buf = [0u8; 4096];
+        let len = file.read_at(&mut buf, 0)?;
+        Ok(String::from_utf8_lossy(&buf[..len]).trim().to_string())
+    })
+}
+
+pub fn write_attribute(path: &str, value: &str) -> std::io::Result<()> {
+    with_attribute(path, |file| {
+        file.write_at(value.as_bytes(), 0)?;
+        Ok(())
+    })
+}
@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// a directory under the system temp dir for fake sysfs trees and state files, removed when dropped
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> TempDir {
+        let unique = format!("asus-px-kb-tool-{}-{}-{}", name, std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(unique);
+        std::fs::create_dir_all(&path).expect("Failed to create temp dir");
+        TempDir { path }
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.to_str().expect("Temp dir is not utf-8")
+    }
+
+    // write a file relative to the temp dir, creating its parents.  returns its full path
+    pub fn write(&self, relative: &str, contents: &str) -> String {
+        let path = self.path.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("Failed to create parent dirs");
+        }
+        std::fs::write(&path, contents).expect("Failed to write temp file");
+        path.to_string_lossy().to_string()
+    }
+
+    pub fn create_dir(&self, relative: &str) -> String {
+        let path = self.path.join(relative);
+        std::fs::create_dir_all(&path).expect("Failed to create temp dir");
+        path.to_string_lossy().to_string()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
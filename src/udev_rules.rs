@@ -0,0 +1,152 @@
+use std::os::unix::fs::PermissionsExt;
+use crate::hid::find_vendor_interface;
+
+static DEFAULT_GROUP: &str = "asus-px-kb-tool";
+static DEFAULT_RULES_PATH: &str = "/etc/udev/rules.d/70-asus-px-keyboard-tool.rules";
+
+// rules giving `group` access to everything the daemon needs apart from bpf.  the hidraw and event
+// rules are narrowed to the vendor interface (usage page 0xff31) if the keyboard is connected
+pub fn generate_rules(group: &str) -> Result<String, String> {
+    let interface_number = find_vendor_interface()
+        .and_then(|bus_path| std::fs::canonicalize(bus_path).ok())
+        .and_then(|hid_device| {
+            // the hid device sits below the usb interface it belongs to
+            let interface = hid_device.parent()?.join("bInterfaceNumber");
+            std::fs::read_to_string(interface).ok()
+        })
+        .map(|number| number.trim().to_string());
+    // sysfs attributes have no GROUP=/MODE=, they are changed from RUN.  RUN needs absolute paths,
+    // which differ between distributions (e.g. NixOS has no /bin/chgrp)
+    let path = std::env::var("PATH").unwrap_or_default();
+    let find = |name: &str| find_binary(name, &path).ok_or(format!("Unable to find {} in PATH", name));
+    let (chgrp, chmod) = (find("chgrp")?, find("chmod")?);
+    Ok(format_rules(group, interface_number.as_deref(), &chgrp, &chmod))
+}
+
+// first executable called `name` in a PATH style list of directories
+fn find_binary(name: &str, path: &str) -> Option<String> {
+    path.split(':')
+        .filter(|dir| dir.starts_with('/'))
+        .map(|dir| format!("{}/{}", dir.trim_end_matches('/'), name))
+        .find(|candidate| std::fs::metadata(candidate)
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0))
+}
+
+fn format_rules(group: &str, interface_number: Option<&str>, chgrp: &str, chmod: &str) -> String {
+    // the vendor interface of the keyboard, or the whole keyboard if it isn't connected
+    let device_match = match interface_number {
+        Some(number) => format!(
+            "ATTRS{{idVendor}}==\"0b05\", ATTRS{{idProduct}}==\"19b6\", ATTRS{{bInterfaceNumber}}==\"{}\"",
+            number
+        ),
+        None => "ATTRS{idVendor}==\"0b05\", ATTRS{idProduct}==\"19b6\"".to_string(),
+    };
+
+    let mut rules = String::new();
+    rules.push_str("# generated by asus-px-keyboard-tool install-udev-rules\n\n");
+    rules.push_str("# fn-lock feature reports go to the hidraw node of the vendor interface (usage page 0xff31)\n");
+    rules.push_str(&format!("SUBSYSTEM==\"hidraw\", {}, GROUP=\"{}\", MODE=\"0660\"\n", device_match, group));
+    rules.push_str("\n# hotkeys of the vendor interface, the tablet mode switch and the lid switch\n");
+    rules.push_str(&format!(
+        "SUBSYSTEM==\"input\", KERNEL==\"event*\", {}, GROUP=\"{}\", MODE=\"0660\"\n",
+        device_match, group
+    ));
+    rules.push_str(&format!(
+        "SUBSYSTEM==\"input\", KERNEL==\"event*\", ATTRS{{name}}==\"Asus WMI hotkeys\", GROUP=\"{}\", MODE=\"0660\"\n",
+        group
+    ));
+    rules.push_str(&format!(
+        "SUBSYSTEM==\"input\", KERNEL==\"event*\", ATTRS{{name}}==\"Lid Switch\", GROUP=\"{}\", MODE=\"0660\"\n",
+        group
+    ));
+    rules.push_str("\n# keyboard backlight and the kernel fn_lock attribute\n");
+    rules.push_str(&format!(
+        "SUBSYSTEM==\"leds\", KERNEL==\"*kbd_backlight*\", RUN+=\"{} {} /sys%p/brightness\", RUN+=\"{} g+w /sys%p/brightness\"\n",
+        chgrp, group, chmod
+    ));
+    rules.push_str(&format!(
+        "SUBSYSTEM==\"platform\", KERNEL==\"asus-nb-wmi\", TEST==\"fn_lock\", RUN+=\"{} {} /sys%p/fn_lock\", RUN+=\"{} g+w /sys%p/fn_lock\"\n",
+        chgrp, group, chmod
+    ));
+    rules // return value
+}
+
+// install-udev-rules [group] [rules path, "-" to print them]
+pub fn install_udev_rules(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let group = args.first().map(|group| group.as_str()).unwrap_or(DEFAULT_GROUP);
+    let rules_path = args.get(1).map(|path| path.as_str()).unwrap_or(DEFAULT_RULES_PATH);
+    let rules = generate_rules(group)?;
+
+    if rules_path == "-" {
+        print!("{}", rules);
+        return Ok(());
+    }
+    std::fs::write(rules_path, rules)
+        .map_err(|e| format!("Unable to write {}: {}", rules_path, e))?;
+    println!("Wrote udev rules to {}", rules_path);
+
+    // apply them to the devices that are already there
+    let udevadm = |args: &[&str]| -> Result<(), String> {
+        let status = std::process::Command::new("udevadm").args(args).status()
+            .map_err(|e| e.to_string())?;
+        if !status.success() {
+            return Err(format!("udevadm {} failed with {}", args.join(" "), status));
+        }
+        Ok(())
+    };
+    if let Err(e) = udevadm(&["control", "--reload"]).and_then(|_| udevadm(&["trigger"])) {
+        eprintln!("Unable to reload udev rules, run `udevadm control --reload && udevadm trigger`: {}", e);
+    }
+    println!("Add your user to the {} group (e.g. `usermod -aG {} $USER`) and log in again", group, group);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn narrows_to_the_vendor_interface() {
+        let rules = format_rules("kbd", Some("02"), "/bin/chgrp", "/bin/chmod");
+        let device_match = "ATTRS{idVendor}==\"0b05\", ATTRS{idProduct}==\"19b6\", ATTRS{bInterfaceNumber}==\"02\"";
+        let hidraw = rules.lines().find(|line| line.starts_with("SUBSYSTEM==\"hidraw\"")).unwrap();
+        assert_eq!(hidraw, format!("SUBSYSTEM==\"hidraw\", {}, GROUP=\"kbd\", MODE=\"0660\"", device_match));
+        assert!(rules.lines().any(|line| line.contains("KERNEL==\"event*\"") && line.contains(device_match)));
+    }
+
+    #[test]
+    fn matches_the_whole_keyboard_without_an_interface() {
+        let rules = format_rules("kbd", None, "/bin/chgrp", "/bin/chmod");
+        assert!(!rules.contains("bInterfaceNumber"));
+        assert!(rules.contains("SUBSYSTEM==\"hidraw\", ATTRS{idVendor}==\"0b05\", ATTRS{idProduct}==\"19b6\", GROUP=\"kbd\""));
+    }
+
+    #[test]
+    fn substitutes_the_group_and_binaries() {
+        let rules = format_rules("input-users", None, "/run/current-system/sw/bin/chgrp", "/run/current-system/sw/bin/chmod");
+        for line in rules.lines().filter(|line| line.starts_with("SUBSYSTEM")) {
+            assert!(line.contains("input-users"), "no group in {}", line);
+        }
+        assert!(rules.contains("RUN+=\"/run/current-system/sw/bin/chgrp input-users /sys%p/brightness\""));
+        assert!(rules.contains("RUN+=\"/run/current-system/sw/bin/chmod g+w /sys%p/fn_lock\""));
+        assert!(!rules.contains("RUN+=\"/bin/"));
+    }
+
+    #[test]
+    fn finds_executables_in_path_order() {
+        let dir = TempDir::new("udev-path");
+        let plain = dir.write("a/chgrp", "");
+        let first = dir.write("b/chgrp", "");
+        let second = dir.write("c/chgrp", "");
+        for path in [&first, &second] {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        std::fs::set_permissions(&plain, std::fs::Permissions::from_mode(0o644)).unwrap();
+        dir.create_dir("d/chgrp");
+
+        let path = format!("relative:{0}/d:{0}/a:{0}/b/:{0}/c", dir.path());
+        assert_eq!(find_binary("chgrp", &path), Some(first));
+        assert_eq!(find_binary("chmod", &path), None);
+    }
+}
@@ -0,0 +1,319 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, Read, Write};
+use std::time::Duration;
+
+static UHID_PATH: &str = "/dev/uhid";
+static DEVICE_NAME: &str = "Asus Virtual N-KEY Device";
+static VENDOR_ID: u32 = 0x0b05;
+static PRODUCT_ID: u32 = 0x19b6;
+static BUS_USB: u16 = 0x03;
+
+// written by hand, not captured from a keyboard: only the vendor collection of the PX keyboard's
+// interface, 0x5a input reports carrying a scancode and 0x5a feature reports for commands like
+// fn-lock.  the real interface also has keyboard and consumer control collections, replay a
+// recording with its descriptor to test against those
+pub static SYNTHETIC_REPORT_DESCRIPTOR: [u8; 42] = [
+    0x06, 0x31, 0xff,       // usage page (vendor 0xff31)
+    0x09, 0x76,             // usage (0x76)
+    0xa1, 0x01,             // collection (application)
+    0x85, 0x5a,             //   report id (0x5a)
+    0x19, 0x00,             //   usage minimum (0)
+    0x2a, 0xff, 0x00,       //   usage maximum (0xff)
+    0x15, 0x00,             //   logical minimum (0)
+    0x26, 0xff, 0x00,       //   logical maximum (0xff)
+    0x75, 0x08,             //   report size (8)
+    0x95, 0x05,             //   report count (5)
+    0x81, 0x00,             //   input (data, array, abs)
+    0x19, 0x00,             //   usage minimum (0)
+    0x2a, 0xff, 0x00,       //   usage maximum (0xff)
+    0x15, 0x00,             //   logical minimum (0)
+    0x26, 0xff, 0x00,       //   logical maximum (0xff)
+    0x75, 0x08,             //   report size (8)
+    0x95, 0x3f,             //   report count (63)
+    0xb1, 0x02,             //   feature (data, var, abs)
+    0xc0,                   // end collection
+];
+static KEY_REPORT_ID: u8 = 0x5a;
+static KEY_REPORT_LEN: usize = 6;
+static FEATURE_REPORT_LEN: usize = 64;
+
+// struct uhid_event from linux/uhid.h, a u32 type followed by the largest request (create2)
+const UHID_EVENT_SIZE: usize = 4 + 128 + 64 + 64 + 2 + 2 + 4 + 4 + 4 + 4 + 4096;
+const UHID_DESTROY: u32 = 1;
+const UHID_START: u32 = 2;
+const UHID_STOP: u32 = 3;
+const UHID_OPEN: u32 = 4;
+const UHID_CLOSE: u32 = 5;
+const UHID_OUTPUT: u32 = 6;
+const UHID_GET_REPORT: u32 = 9;
+const UHID_GET_REPORT_REPLY: u32 = 10;
+const UHID_CREATE2: u32 = 11;
+const UHID_INPUT2: u32 = 12;
+const UHID_SET_REPORT: u32 = 13;
+const UHID_SET_REPORT_REPLY: u32 = 14;
+const EIO: u16 = 5;
+
+// a kernel hid device that looks like the PX keyboard's vendor interface.  removed when dropped
+pub struct VirtualKeyboard {
+    uhid: File,
+    // last feature report the host set, returned on get report
+    feature_report: [u8; FEATURE_REPORT_LEN],
+}
+
+impl VirtualKeyboard {
+    pub fn create() -> std::io::Result<VirtualKeyboard> {
+        VirtualKeyboard::create_with_descriptor(&SYNTHETIC_REPORT_DESCRIPTOR)
+    }
+
+    // same ids, another report descriptor, e.g. one recorded from a real keyboard
+    pub fn create_with_descriptor(descriptor: &[u8]) -> std::io::Result<VirtualKeyboard> {
+        if descriptor.len() > 4096 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "report descriptor is longer than 4096 bytes"));
+        }
+        let mut uhid = OpenOptions::new().read(true).write(true).open(UHID_PATH)?;
+
+        let mut event = [0u8; UHID_EVENT_SIZE];
+        event[0..4].copy_from_slice(&UHID_CREATE2.to_ne_bytes());
+        let request = &mut event[4..];
+        request[..DEVICE_NAME.len()].copy_from_slice(DEVICE_NAME.as_bytes());
+        // phys and uniq stay empty
+        let mut offset = 128 + 64 + 64;
+        request[offset..offset + 2].copy_from_slice(&(descriptor.len() as u16).to_ne_bytes());
+        request[offset + 2..offset + 4].copy_from_slice(&BUS_USB.to_ne_bytes());
+        request[offset + 4..offset + 8].copy_from_slice(&VENDOR_ID.to_ne_bytes());
+        request[offset + 8..offset + 12].copy_from_slice(&PRODUCT_ID.to_ne_bytes());
+        // version and country stay 0
+        offset += 20;
+        request[offset..offset + descriptor.len()].copy_from_slice(descriptor);
+        uhid.write_all(&event)?;
+
+        let mut feature_report = [0u8; FEATURE_REPORT_LEN];
+        feature_report[0] = KEY_REPORT_ID;
+        Ok(VirtualKeyboard { uhid, feature_report })
+    }
+
+    // second handle for injecting key events from another thread
+    pub fn injector(&self) -> std::io::Result<KeyInjector> {
+        Ok(KeyInjector { uhid: self.uhid.try_clone()? })
+    }
+
+    // block until the kernel sends the next event and answer it.  returns false once the device is stopped
+    pub fn process_event(&mut self) -> std::io::Result<bool> {
+        let mut event = [0u8; UHID_EVENT_SIZE];
+        let read = self.uhid.read(&mut event)?;
+        if read < 4 {
+            return Ok(true);
+        }
+        let event_type = u32::from_ne_bytes(event[0..4].try_into().unwrap());
+        let request = &event[4..];
+        match event_type {
+            UHID_START => println!("Device started"),
+            UHID_STOP => {
+                println!("Device stopped");
+                return Ok(false);
+            }
+            UHID_OPEN => println!("Device opened"),
+            UHID_CLOSE => println!("Device closed"),
+            UHID_OUTPUT => {}
+            UHID_GET_REPORT => {
+                let id = u32::from_ne_bytes(request[0..4].try_into().unwrap());
+                let report_number = request[4];
+                if report_number == KEY_REPORT_ID {
+                    let report = self.feature_report;
+                    self.reply_get_report(id, 0, &report)?;
+                } else {
+                    self.reply_get_report(id, EIO, &[])?;
+                }
+            }
+            UHID_SET_REPORT => {
+                let id = u32::from_ne_bytes(request[0..4].try_into().unwrap());
+                let report_number = request[4];
+                let size = u16::from_ne_bytes(request[6..8].try_into().unwrap()) as usize;
+                let data = &request[8..8 + size.min(FEATURE_REPORT_LEN)];
+                let error = if report_number == KEY_REPORT_ID { 0 } else { EIO };
+                if error == 0 {
+                    self.handle_feature_report(data);
+                }
+                self.reply_set_report(id, error)?;
+            }
+            other => println!("Ignoring uhid event {}", other),
+        }
+        Ok(true)
+    }
+
+    fn handle_feature_report(&mut self, data: &[u8]) {
+        self.feature_report = [0u8; FEATURE_REPORT_LEN];
+        self.feature_report[..data.len()].copy_from_slice(data);
+        // 0x5a 0xd0 0x4e <0 = on, 1 = off>, see hid::toggle_fn_lock
+        if data.len() >= 4 && data[1] == 0xd0 && data[2] == 0x4e {
+            let fn_lock = data[3] == 0;
+            println!("Fn-Lock set to {}", if fn_lock { "on" } else { "off" });
+        } else {
+            println!("Feature report {:02x?}", &data[..data.len().min(8)]);
+        }
+    }
+
+    fn reply_get_report(&mut self, id: u32, error: u16, data: &[u8]) -> std::io::Result<()> {
+        let mut event = [0u8; UHID_EVENT_SIZE];
+        event[0..4].copy_from_slice(&UHID_GET_REPORT_REPLY.to_ne_bytes());
+        event[4..8].copy_from_slice(&id.to_ne_bytes());
+        event[8..10].copy_from_slice(&error.to_ne_bytes());
+        event[10..12].copy_from_slice(&(data.len() as u16).to_ne_bytes());
+        event[12..12 + data.len()].copy_from_slice(data);
+        self.uhid.write_all(&event)
+    }
+
+    fn reply_set_report(&mut self, id: u32, error: u16) -> std::io::Result<()> {
+        let mut event = [0u8; UHID_EVENT_SIZE];
+        event[0..4].copy_from_slice(&UHID_SET_REPORT_REPLY.to_ne_bytes());
+        event[4..8].copy_from_slice(&id.to_ne_bytes());
+        event[8..10].copy_from_slice(&error.to_ne_bytes());
+        self.uhid.write_all(&event)
+    }
+}
+
+impl Drop for VirtualKeyboard {
+    fn drop(&mut self) {
+        // closing the fd would do the same, this makes it explicit
+        let mut event = [0u8; UHID_EVENT_SIZE];
+        event[0..4].copy_from_slice(&UHID_DESTROY.to_ne_bytes());
+        let _ = self.uhid.write_all(&event);
+    }
+}
+
+pub struct KeyInjector {
+    uhid: File,
+}
+
+impl KeyInjector {
+    // press and release a key the way the keyboard does: a 0x5a report with the scancode, then
+    // one with 0
+    pub fn tap(&mut self, scancode: u8) -> std::io::Result<()> {
+        self.send_report(scancode)?;
+        std::thread::sleep(Duration::from_millis(20));
+        self.send_report(0)
+    }
+
+    fn send_report(&mut self, scancode: u8) -> std::io::Result<()> {
+        let mut report = [0u8; KEY_REPORT_LEN];
+        report[0] = KEY_REPORT_ID;
+        report[1] = scancode;
+        self.send_input(&report)
+    }
+
+    // an input report exactly as the keyboard would send it, report id first
+    pub fn send_input(&mut self, report: &[u8]) -> std::io::Result<()> {
+        if report.len() > 4096 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "input report is longer than 4096 bytes"));
+        }
+        let mut event = [0u8; UHID_EVENT_SIZE];
+        event[0..4].copy_from_slice(&UHID_INPUT2.to_ne_bytes());
+        event[4..6].copy_from_slice(&(report.len() as u16).to_ne_bytes());
+        event[6..6 + report.len()].copy_from_slice(report);
+        self.uhid.write_all(&event)
+    }
+
+    // remove the device.  the kernel answers with a stop event, which ends process_event
+    pub fn destroy(&mut self) -> std::io::Result<()> {
+        let mut event = [0u8; UHID_EVENT_SIZE];
+        event[0..4].copy_from_slice(&UHID_DESTROY.to_ne_bytes());
+        self.uhid.write_all(&event)
+    }
+}
+
+fn parse_scancode(value: &str) -> Result<u8, String> {
+    let digits = value.trim().trim_start_matches("0x").trim_start_matches("0X");
+    u8::from_str_radix(digits, 16).map_err(|e| format!("Invalid scancode {}: {}", value.trim(), e))
+}
+
+// simulate [scancode...]: create the virtual keyboard, tap the given scancodes (hex) once it is up,
+// then tap one per line read from stdin.  runs until killed
+pub fn run_simulate(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let scancodes = args.iter().map(|arg| parse_scancode(arg)).collect::<Result<Vec<u8>, String>>()?;
+
+    let mut keyboard = VirtualKeyboard::create()
+        .map_err(|e| format!("Unable to create uhid device via {} (needs root or uhid access): {}", UHID_PATH, e))?;
+    println!("Created {} {:04X}:{:04X}", DEVICE_NAME, VENDOR_ID, PRODUCT_ID);
+
+    let mut injector = keyboard.injector()?;
+    std::thread::spawn(move || {
+        // give the kernel and hid-asus time to bind before the first report
+        std::thread::sleep(Duration::from_millis(500));
+        for scancode in scancodes {
+            if let Err(e) = injector.tap(scancode) {
+                eprintln!("Unable to inject scancode {:#04x}: {}", scancode, e);
+            }
+        }
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match parse_scancode(&line) {
+                Ok(scancode) => {
+                    if let Err(e) = injector.tap(scancode) {
+                        eprintln!("Unable to inject scancode {:#04x}: {}", scancode, e);
+                    } else {
+                        println!("Injected scancode {:#04x}", scancode);
+                    }
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    });
+
+    while keyboard.process_event()? {}
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use hidapi::HidApi;
+    use crate::hid::{find_vendor_interface, hidraw_node, toggle_fn_lock};
+
+    // bus path of the virtual keyboard, once the kernel has bound it
+    fn wait_for_interface() -> Option<String> {
+        for _ in 0..50 {
+            let interfaces = std::fs::read_dir("/sys/bus/hid/devices").into_iter().flatten().flatten();
+            for interface in interfaces {
+                let uevent = std::fs::read_to_string(interface.path().join("uevent")).unwrap_or_default();
+                let bus_path = interface.path().to_string_lossy().to_string();
+                if uevent.lines().any(|line| line == format!("HID_NAME={}", DEVICE_NAME)) && hidraw_node(&bus_path).is_some() {
+                    return Some(bus_path);
+                }
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        None
+    }
+
+    #[test]
+    #[ignore = "needs /dev/uhid, run as root with --ignored"]
+    fn fn_lock_round_trip() {
+        let mut keyboard = VirtualKeyboard::create().expect("Unable to create uhid device");
+        let mut injector = keyboard.injector().unwrap();
+        let events = std::thread::spawn(move || while keyboard.process_event().unwrap_or(false) {});
+
+        let bus_path = wait_for_interface().expect("Virtual keyboard not bound");
+        // a real keyboard sorts before the virtual one, either has the vendor collection
+        assert!(find_vendor_interface().is_some());
+        let hidraw_path = hidraw_node(&bus_path).unwrap();
+
+        for (state, value) in [(true, 0x00), (false, 0x01)] {
+            assert!(toggle_fn_lock(&hidraw_path, state));
+            let device = HidApi::new().unwrap().open_path(&CString::new(hidraw_path.clone()).unwrap()).unwrap();
+            let mut report = [0u8; FEATURE_REPORT_LEN];
+            report[0] = KEY_REPORT_ID;
+            let len = device.get_feature_report(&mut report).unwrap();
+            assert!(len >= 4);
+            assert_eq!(report[..4], [KEY_REPORT_ID, 0xd0, 0x4e, value]);
+        }
+
+        injector.destroy().unwrap();
+        events.join().unwrap();
+    }
+}
@@ -4,4 +4,5 @@ rm /usr/local/bin/asus-px-keyboard-tool
 rm /etc/systemd/system/asus-px-keyboard-tool.service
 rm /etc/systemd/system/asus-px-keyboard-tool-restore.service || true
 rm /etc/asus-px-keyboard-tool.conf
-rm -rf /var/lib/asus-px-kb-tool/ || true
\ No newline at end of file
+rm -rf /var/lib/asus-px-kb-tool/ || true
+rm /etc/udev/rules.d/70-asus-px-keyboard-tool.rules || true